@@ -0,0 +1,59 @@
+use std::time::{Duration, Instant};
+
+/// The clock is only read every this many steps.
+const CLOCK_EVERY: u64 = 1024;
+
+/// Why a script was stopped before it finished.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interrupt {
+    /// It took more steps than allowed
+    Steps(u64),
+    /// It ran for longer than the timeout
+    Timeout(Duration),
+}
+
+/// Limits how much a script can run: a number of steps, a timeout or both.
+/// The tree walking interpreter takes a step for every statement it executes
+/// and every expression it evaluates, the virtual machine for every
+/// instruction. Both start over on every call to `interpret`.
+#[derive(Debug, Default)]
+pub struct Budget {
+    steps: Option<u64>,
+    timeout: Option<Duration>,
+    taken: u64,
+    deadline: Option<Instant>,
+}
+
+impl Budget {
+    pub fn set_steps(&mut self, steps: u64) {
+        self.steps = Some(steps);
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = Some(timeout);
+    }
+
+    /// Gets the whole budget back, a script is about to run.
+    pub fn start(&mut self) {
+        self.taken = 0;
+        self.deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+    }
+
+    pub fn step(&mut self) -> Result<(), Interrupt> {
+        self.taken += 1;
+
+        if let Some(steps) = self.steps {
+            if self.taken > steps {
+                return Err(Interrupt::Steps(steps));
+            }
+        }
+
+        if let (Some(deadline), Some(timeout)) = (self.deadline, self.timeout) {
+            if self.taken.is_multiple_of(CLOCK_EVERY) && Instant::now() >= deadline {
+                return Err(Interrupt::Timeout(timeout));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,296 @@
+use crate::{
+    errors::CompileError,
+    expr::{Ast, Expr, ExprId, TokenId},
+    statements::{FunctionDecl, Pattern, Statement},
+    symbol::Symbol,
+    token::{Token, TokenType},
+    types::{Annotation, Type},
+};
+
+use std::collections::HashMap;
+
+/// What the checker knows about a name.
+#[derive(Clone)]
+enum Binding {
+    Var(Option<Annotation>),
+    Function(Vec<Option<Annotation>>, Option<Annotation>),
+    /// The variants of an enum with their payload sizes
+    Enum(Symbol, Vec<(Symbol, usize)>),
+}
+
+/// Static type checker, it infers the types of expressions and reports every
+/// place where they disagree with an annotation. Unannotated code is `any`.
+pub struct Checker<'a> {
+    ast: &'a Ast,
+    scopes: Vec<HashMap<Symbol, Binding>>,
+    /// The return annotation of every function being checked, innermost last
+    returns: Vec<Option<Annotation>>,
+    errors: Vec<CompileError>,
+}
+
+impl<'a> Checker<'a> {
+    pub fn check(statements: &[Statement], ast: &'a Ast) -> Vec<CompileError> {
+        let mut checker = Self {
+            ast,
+            scopes: vec![HashMap::new()],
+            returns: Vec::new(),
+            errors: Vec::new(),
+        };
+
+        checker.block(statements);
+        checker.errors
+    }
+
+    fn block(&mut self, statements: &[Statement]) {
+        // Functions can be called before their declaration is checked, from
+        // other functions or recursively.
+        for stmt in statements {
+            if let Statement::Function(function) = stmt {
+                self.declare_function(function);
+            }
+        }
+
+        for stmt in statements {
+            self.statement(stmt);
+        }
+    }
+
+    fn scoped(&mut self, bindings: Vec<(Symbol, Binding)>, statements: &[Statement]) {
+        self.scopes.push(bindings.into_iter().collect());
+        self.block(statements);
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token, binding: Binding) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), binding);
+        }
+    }
+
+    fn declare_function(&mut self, function: &FunctionDecl) {
+        let params = function
+            .params
+            .iter()
+            .map(|p| p.annotation.clone())
+            .collect();
+
+        self.declare(
+            &function.name,
+            Binding::Function(params, function.returns.clone()),
+        );
+    }
+
+    fn lookup(&self, name: &Symbol) -> Option<&Binding> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn expect(&mut self, annotation: &Option<Annotation>, found: Type, place: (usize, usize)) {
+        if let Some(annotation) = annotation {
+            if !annotation.ty.accepts(&found) {
+                self.errors.push(CompileError::TypeMismatch(
+                    (annotation.place, annotation.ty.clone()),
+                    (place, found),
+                ));
+            }
+        }
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Print(expr) | Statement::Expresion(expr) => {
+                self.infer(*expr);
+            }
+            Statement::Var(var) => {
+                if let Some(initializer) = var.initializer {
+                    let found = self.infer(initializer);
+                    self.expect(&var.annotation, found, self.ast.place(initializer));
+                }
+
+                self.declare(&var.name, Binding::Var(var.annotation.clone()));
+            }
+            Statement::Unpack(names, expr) => {
+                self.infer(*expr);
+
+                for name in names {
+                    self.declare(name, Binding::Var(None));
+                }
+            }
+            Statement::Block(statements) => self.scoped(Vec::new(), statements),
+            Statement::If(condition, then_branch, else_branch) => {
+                self.infer(*condition);
+                self.statement(then_branch);
+
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            Statement::While(condition, body) => {
+                self.infer(*condition);
+                self.statement(body);
+            }
+            Statement::Function(function) => {
+                self.declare_function(function);
+
+                let params = function
+                    .params
+                    .iter()
+                    .map(|p| (p.name.lexeme.clone(), Binding::Var(p.annotation.clone())))
+                    .collect();
+
+                self.returns.push(function.returns.clone());
+                self.scoped(params, &function.body);
+                self.returns.pop();
+            }
+            Statement::Return(_, expr) => {
+                let found = self.infer(*expr);
+
+                if let Some(returns) = self.returns.last().cloned() {
+                    self.expect(&returns, found, self.ast.place(*expr));
+                }
+            }
+            Statement::Enum(decl) => {
+                let variants = decl
+                    .variants
+                    .iter()
+                    .map(|(name, fields)| (name.lexeme.clone(), fields.len()))
+                    .collect();
+
+                self.declare(
+                    &decl.name,
+                    Binding::Enum(decl.name.lexeme.clone(), variants),
+                );
+            }
+            Statement::Match(_, scrutinee, arms) => {
+                self.infer(*scrutinee);
+
+                for (pattern, arm) in arms {
+                    let bindings = match pattern {
+                        Pattern::Variant(_, _, _, bindings) => bindings
+                            .iter()
+                            .map(|b| (b.lexeme.clone(), Binding::Var(None)))
+                            .collect(),
+                        Pattern::Wildcard(_) => Vec::new(),
+                    };
+
+                    self.scoped(bindings, std::slice::from_ref(arm));
+                }
+            }
+        }
+    }
+
+    fn infer(&mut self, expr: ExprId) -> Type {
+        let ast = self.ast;
+
+        match ast[expr] {
+            Expr::Literal(value) => Type::of(&ast[value]),
+            Expr::This(_) | Expr::Super(..) => Type::Any,
+            Expr::Grouping(expr) => self.infer(expr),
+            Expr::Tuple(elements) => {
+                for element in &ast[elements] {
+                    self.infer(*element);
+                }
+
+                Type::Tuple
+            }
+            Expr::Unary(op, rhs) => {
+                self.infer(rhs);
+
+                match ast[op]._type {
+                    TokenType::Bang => Type::Bool,
+                    _ => Type::Number,
+                }
+            }
+            Expr::Binary(lhs, op, rhs) => {
+                let lhs = self.infer(lhs);
+                let rhs = self.infer(rhs);
+
+                match ast[op]._type {
+                    TokenType::Plus if lhs == Type::String && rhs == Type::String => Type::String,
+                    TokenType::Plus if lhs == Type::Number && rhs == Type::Number => Type::Number,
+                    TokenType::Plus => Type::Any,
+                    TokenType::Minus | TokenType::Slash | TokenType::Star | TokenType::Percent => {
+                        Type::Number
+                    }
+                    _ => Type::Bool,
+                }
+            }
+            Expr::Logical(lhs, _, rhs) => {
+                let lhs = self.infer(lhs);
+                let rhs = self.infer(rhs);
+
+                if lhs == rhs {
+                    lhs
+                } else {
+                    Type::Any
+                }
+            }
+            Expr::Variable(name, _) => match self.lookup(&ast[name].lexeme) {
+                Some(Binding::Var(Some(annotation))) => annotation.ty.clone(),
+                Some(Binding::Function(..)) => Type::Function,
+                _ => Type::Any,
+            },
+            Expr::Assign(name, _, value) => {
+                let found = self.infer(value);
+
+                if let Some(Binding::Var(annotation)) = self.lookup(&ast[name].lexeme).cloned() {
+                    self.expect(&annotation, found.clone(), ast.place(value));
+                }
+
+                found
+            }
+            Expr::Get(object, name) => match self.variant(object, name) {
+                Some((ty, 0)) => Type::Enum(ty.to_string()),
+                Some(_) => Type::Function,
+                None => Type::Any,
+            },
+            Expr::Call(callee, _, args) => {
+                let args = &ast[args];
+                let found: Vec<_> = args.iter().map(|arg| self.infer(*arg)).collect();
+
+                if let Expr::Get(object, name) = ast[callee] {
+                    return match self.variant(object, name) {
+                        Some((ty, _)) => Type::Enum(ty.to_string()),
+                        None => Type::Any,
+                    };
+                }
+
+                let binding = match ast[callee] {
+                    Expr::Variable(name, _) => self.lookup(&ast[name].lexeme).cloned(),
+                    _ => {
+                        self.infer(callee);
+                        None
+                    }
+                };
+
+                match binding {
+                    // Wrong arity is a runtime error, not a type error
+                    Some(Binding::Function(params, returns)) if params.len() == args.len() => {
+                        for ((param, found), arg) in params.iter().zip(found).zip(args) {
+                            self.expect(param, found, ast.place(*arg));
+                        }
+
+                        returns.map_or(Type::Any, |a| a.ty)
+                    }
+                    _ => Type::Any,
+                }
+            }
+        }
+    }
+
+    /// The enum name and payload size of `Enum.Variant`, if it is one.
+    fn variant(&mut self, object: ExprId, name: TokenId) -> Option<(Symbol, usize)> {
+        let ast = self.ast;
+
+        if let Expr::Variable(object, _) = ast[object] {
+            if let Some(Binding::Enum(ty, variants)) = self.lookup(&ast[object].lexeme) {
+                return variants
+                    .iter()
+                    .find(|(variant, _)| *variant == ast[name].lexeme)
+                    .map(|(_, fields)| (ty.clone(), *fields));
+            }
+        }
+
+        self.infer(object);
+        None
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{expr::Value, types::Annotation};
+
+use std::rc::Rc;
+
+/// An instruction of the virtual machine. Operands index into the tables of
+/// the [`Chunk`] that holds the instruction, jumps are absolute positions in
+/// its code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Constant(u32),
+    Nil,
+    Pop,
+    /// Slot in the current frame, the called function is in slot 0
+    GetLocal(u32),
+    SetLocal(u32),
+    GetUpvalue(u32),
+    SetUpvalue(u32),
+    /// Slot in the global table of the machine
+    GetGlobal(u32),
+    SetGlobal(u32),
+    DefineGlobal(u32),
+    Negate,
+    Not,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Jump(u32),
+    /// Jumps if the value on top is falsey, it is left on the stack
+    JumpIfFalse(u32),
+    JumpIfTrue(u32),
+    /// Index of the [`CallSite`]
+    Call(u32),
+    /// A call in `return f(args);`, a user function replaces the frame of the
+    /// caller instead of pushing a new one. A `Return` follows it for the
+    /// other callables.
+    TailCall(u32),
+    /// Index of the [`Proto`] in the chunk's functions
+    Closure(u32),
+    /// Moves the local on top to the heap and pops it
+    CloseUpvalue,
+    Return,
+    Print,
+    /// Builds a tuple out of that many values
+    Tuple(u32),
+    /// Replaces a tuple with its elements, there must be that many
+    Unpack(u32),
+    /// Property of an enum, the constant is the name
+    Get(u32),
+    /// Creates the enum described by the constant
+    Enum(u32),
+    /// Pops an enum and pushes whether the value below it is the variant of
+    /// the [`PatternInfo`]
+    Pattern(u32),
+    /// Pushes the payload of the enum value on top
+    Destructure,
+}
+
+/// Compiled code with everything its instructions refer to.
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<Op>,
+    /// The source code that produced every instruction, for errors
+    pub spans: Vec<(usize, usize)>,
+    pub constants: Vec<Value>,
+    pub functions: Vec<Rc<Proto>>,
+    pub calls: Vec<CallSite>,
+    pub patterns: Vec<PatternInfo>,
+}
+
+impl Chunk {
+    pub fn write(&mut self, op: Op, span: (usize, usize)) -> usize {
+        self.code.push(op);
+        self.spans.push(span);
+        self.code.len() - 1
+    }
+
+    pub fn constant(&mut self, value: Value) -> u32 {
+        self.constants.push(value);
+        (self.constants.len() - 1) as u32
+    }
+}
+
+/// A compiled function, closures are created out of it at runtime.
+#[derive(Debug, Default)]
+pub struct Proto {
+    pub name: String,
+    pub params: Vec<Option<Annotation>>,
+    pub returns: Option<Annotation>,
+    /// Where every upvalue comes from: a local of the enclosing function when
+    /// `true`, or one of its upvalues
+    pub upvalues: Vec<(bool, u32)>,
+    pub chunk: Chunk,
+}
+
+/// What a call needs besides the values on the stack.
+#[derive(Debug)]
+pub struct CallSite {
+    pub args: Vec<(usize, usize)>,
+    /// The whole call, where a returned value comes from
+    pub place: (usize, usize),
+}
+
+/// A `Enum.Variant(bindings)` pattern, the enum is looked up at runtime.
+#[derive(Debug)]
+pub struct PatternInfo {
+    pub ty: String,
+    pub variant: String,
+    pub variant_place: (usize, usize),
+    pub bindings: usize,
+}
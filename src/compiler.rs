@@ -0,0 +1,543 @@
+use crate::{
+    chunk::{CallSite, Op, PatternInfo, Proto},
+    expr::{Ast, Enum, Expr, ExprId, ExprList, TokenId, Value},
+    statements::{FunctionDecl, Pattern, Statement},
+    token::{Token, TokenType},
+};
+
+use std::{collections::HashMap, rc::Rc};
+
+/// The names of the global variables, each one has a slot in the machine.
+/// It outlives a single compilation so the REPL can keep its globals.
+#[derive(Debug, Default)]
+pub struct Globals {
+    slots: HashMap<String, u32>,
+    pub names: Vec<String>,
+}
+
+impl Globals {
+    pub fn slot(&mut self, name: &str) -> u32 {
+        if let Some(slot) = self.slots.get(name) {
+            return *slot;
+        }
+
+        self.names.push(name.into());
+        self.slots.insert(name.into(), self.names.len() as u32 - 1);
+        self.names.len() as u32 - 1
+    }
+}
+
+struct Local {
+    name: String,
+    depth: usize,
+    /// Whether a closure refers to it, so it has to outlive its slot
+    captured: bool,
+}
+
+/// The function being compiled.
+struct FunctionState {
+    proto: Proto,
+    locals: Vec<Local>,
+    depth: usize,
+}
+
+/// Compiles resolved statements to bytecode for the [`Vm`](crate::vm::Vm).
+///
+/// Locals live in stack slots, in the same scopes the resolver and the tree
+/// walking interpreter use: one per block, per function call and per match
+/// arm. Locals captured by closures become upvalues. Top level declarations
+/// are globals.
+pub struct Compiler<'g> {
+    ast: &'g Ast,
+    globals: &'g mut Globals,
+    /// The enclosing functions, innermost last
+    functions: Vec<FunctionState>,
+}
+
+impl<'g> Compiler<'g> {
+    pub fn compile(statements: &[Statement], ast: &'g Ast, globals: &'g mut Globals) -> Proto {
+        let mut compiler = Self {
+            ast,
+            globals,
+            functions: vec![FunctionState {
+                proto: Proto {
+                    name: "script".into(),
+                    ..Default::default()
+                },
+                // The slot of the script itself
+                locals: vec![Local {
+                    name: String::new(),
+                    depth: 0,
+                    captured: false,
+                }],
+                depth: 0,
+            }],
+        };
+
+        for stmt in statements {
+            compiler.statement(stmt);
+        }
+
+        compiler.emit(Op::Nil, (0, 0));
+        compiler.emit(Op::Return, (0, 0));
+
+        compiler
+            .functions
+            .pop()
+            .map(|f| f.proto)
+            .unwrap_or_default()
+    }
+
+    fn current(&mut self) -> &mut FunctionState {
+        self.functions
+            .last_mut()
+            .expect("the script is always compiled")
+    }
+
+    fn emit(&mut self, op: Op, span: (usize, usize)) -> usize {
+        self.current().proto.chunk.write(op, span)
+    }
+
+    fn constant(&mut self, value: Value) -> u32 {
+        self.current().proto.chunk.constant(value)
+    }
+
+    /// The position the next instruction will be at.
+    fn here(&mut self) -> u32 {
+        self.current().proto.chunk.code.len() as u32
+    }
+
+    /// Points the jump at `at` to the next instruction.
+    fn patch(&mut self, at: usize) {
+        let target = self.here();
+
+        match &mut self.current().proto.chunk.code[at] {
+            Op::Jump(to) | Op::JumpIfFalse(to) | Op::JumpIfTrue(to) => *to = target,
+            other => unreachable!("{:?} is not a jump", other),
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.current().depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        let function = self.current();
+        function.depth -= 1;
+
+        let depth = function.depth;
+        let mut ops = Vec::new();
+
+        while let Some(local) = function.locals.last() {
+            if local.depth <= depth {
+                break;
+            }
+
+            ops.push(if local.captured {
+                Op::CloseUpvalue
+            } else {
+                Op::Pop
+            });
+            function.locals.pop();
+        }
+
+        for op in ops {
+            self.emit(op, (0, 0));
+        }
+    }
+
+    fn add_local(&mut self, name: &str) {
+        let function = self.current();
+
+        function.locals.push(Local {
+            name: name.into(),
+            depth: function.depth,
+            captured: false,
+        });
+    }
+
+    /// Stores the value on top of the stack in a new variable.
+    fn define(&mut self, name: &Token) {
+        if self.current().depth == 0 {
+            let slot = self.globals.slot(&name.lexeme);
+            self.emit(Op::DefineGlobal(slot), name.place);
+        } else {
+            self.add_local(&name.lexeme);
+        }
+    }
+
+    fn resolve_local(&self, function: usize, name: &str) -> Option<u32> {
+        self.functions[function]
+            .locals
+            .iter()
+            .rposition(|local| local.name == name)
+            .map(|slot| slot as u32)
+    }
+
+    fn resolve_upvalue(&mut self, function: usize, name: &str) -> Option<u32> {
+        if function == 0 {
+            return None;
+        }
+
+        if let Some(slot) = self.resolve_local(function - 1, name) {
+            self.functions[function - 1].locals[slot as usize].captured = true;
+            return Some(self.add_upvalue(function, true, slot));
+        }
+
+        self.resolve_upvalue(function - 1, name)
+            .map(|upvalue| self.add_upvalue(function, false, upvalue))
+    }
+
+    fn add_upvalue(&mut self, function: usize, local: bool, index: u32) -> u32 {
+        let upvalues = &mut self.functions[function].proto.upvalues;
+
+        match upvalues.iter().position(|u| *u == (local, index)) {
+            Some(existing) => existing as u32,
+            None => {
+                upvalues.push((local, index));
+                upvalues.len() as u32 - 1
+            }
+        }
+    }
+
+    /// The instructions that read and write `name`.
+    fn variable(&mut self, name: &str) -> (Op, Op) {
+        let function = self.functions.len() - 1;
+
+        if let Some(slot) = self.resolve_local(function, name) {
+            (Op::GetLocal(slot), Op::SetLocal(slot))
+        } else if let Some(upvalue) = self.resolve_upvalue(function, name) {
+            (Op::GetUpvalue(upvalue), Op::SetUpvalue(upvalue))
+        } else {
+            let slot = self.globals.slot(name);
+            (Op::GetGlobal(slot), Op::SetGlobal(slot))
+        }
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Print(expr) => {
+                self.expression(*expr);
+                self.emit(Op::Print, self.ast.place(*expr));
+            }
+            Statement::Expresion(expr) => {
+                self.expression(*expr);
+                self.emit(Op::Pop, self.ast.place(*expr));
+            }
+            Statement::Var(var) => {
+                match var.initializer {
+                    Some(initializer) => self.expression(initializer),
+                    None => {
+                        self.emit(Op::Nil, var.name.place);
+                    }
+                }
+
+                self.define(&var.name);
+            }
+            Statement::Unpack(names, expr) => {
+                let place = (names[0].place.0, names[names.len() - 1].place.1);
+
+                self.expression(*expr);
+                self.emit(Op::Unpack(names.len() as u32), place);
+
+                if self.current().depth == 0 {
+                    // The last value is on top
+                    for name in names.iter().rev() {
+                        self.define(name);
+                    }
+                } else {
+                    for name in names {
+                        self.define(name);
+                    }
+                }
+            }
+            Statement::Block(statements) => {
+                self.begin_scope();
+
+                for stmt in statements {
+                    self.statement(stmt);
+                }
+
+                self.end_scope();
+            }
+            Statement::If(condition, then_branch, else_branch) => {
+                let place = self.ast.place(*condition);
+
+                self.expression(*condition);
+                let to_else = self.emit(Op::JumpIfFalse(0), place);
+                self.emit(Op::Pop, place);
+                self.statement(then_branch);
+
+                let to_end = self.emit(Op::Jump(0), place);
+                self.patch(to_else);
+                self.emit(Op::Pop, place);
+
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+
+                self.patch(to_end);
+            }
+            Statement::While(condition, body) => {
+                let start = self.here();
+                let place = self.ast.place(*condition);
+
+                self.expression(*condition);
+                let to_end = self.emit(Op::JumpIfFalse(0), place);
+                self.emit(Op::Pop, place);
+                self.statement(body);
+                self.emit(Op::Jump(start), place);
+
+                self.patch(to_end);
+                self.emit(Op::Pop, place);
+            }
+            Statement::Function(function) => {
+                // Declared before the body so it can call itself
+                if self.current().depth > 0 {
+                    self.add_local(&function.name.lexeme);
+                }
+
+                self.function(function);
+
+                if self.current().depth == 0 {
+                    self.define(&function.name);
+                }
+            }
+            Statement::Return(keyword, expr) => {
+                match self.ast[*expr] {
+                    Expr::Call(callee, paren, args) => self.call(*expr, callee, paren, args, true),
+                    _ => self.expression(*expr),
+                }
+
+                self.emit(Op::Return, keyword.place);
+            }
+            Statement::Enum(decl) => {
+                let ty = Enum {
+                    name: decl.name.lexeme.to_string(),
+                    variants: decl
+                        .variants
+                        .iter()
+                        .map(|(name, fields)| (name.lexeme.to_string(), fields.len()))
+                        .collect(),
+                };
+
+                let constant = self.constant(Value::Enum(Rc::new(ty)));
+                self.emit(Op::Enum(constant), decl.name.place);
+                self.define(&decl.name);
+            }
+            Statement::Match(keyword, scrutinee, arms) => {
+                self.match_statement(keyword, *scrutinee, arms)
+            }
+        }
+    }
+
+    fn function(&mut self, decl: &FunctionDecl) {
+        self.functions.push(FunctionState {
+            proto: Proto {
+                name: decl.name.lexeme.to_string(),
+                params: decl.params.iter().map(|p| p.annotation.clone()).collect(),
+                returns: decl.returns.clone(),
+                ..Default::default()
+            },
+            locals: vec![Local {
+                name: String::new(),
+                depth: 1,
+                captured: false,
+            }],
+            depth: 1,
+        });
+
+        for param in &decl.params {
+            self.add_local(&param.name.lexeme);
+        }
+
+        for stmt in &decl.body {
+            self.statement(stmt);
+        }
+
+        self.emit(Op::Nil, decl.name.place);
+        self.emit(Op::Return, decl.name.place);
+
+        let proto = self.functions.pop().map(|f| f.proto).unwrap_or_default();
+        let chunk = &mut self.current().proto.chunk;
+
+        chunk.functions.push(Rc::new(proto));
+        let index = chunk.functions.len() as u32 - 1;
+        self.emit(Op::Closure(index), decl.name.place);
+    }
+
+    /// The scrutinee stays in a hidden local while the arms are tested in
+    /// order, the first one that matches runs and the rest are skipped.
+    fn match_statement(
+        &mut self,
+        keyword: &Token,
+        scrutinee: ExprId,
+        arms: &[(Pattern, Statement)],
+    ) {
+        self.begin_scope();
+        self.expression(scrutinee);
+        self.add_local("");
+
+        let mut to_end = Vec::new();
+
+        for (pattern, arm) in arms {
+            match pattern {
+                Pattern::Wildcard(_) => {
+                    self.begin_scope();
+                    self.statement(arm);
+                    self.end_scope();
+
+                    to_end.push(self.emit(Op::Jump(0), keyword.place));
+                }
+                Pattern::Variant(ty, _, variant, bindings) => {
+                    let (get, _) = self.variable(&ty.lexeme);
+                    self.emit(get, ty.place);
+
+                    let chunk = &mut self.current().proto.chunk;
+                    chunk.patterns.push(PatternInfo {
+                        ty: ty.lexeme.to_string(),
+                        variant: variant.lexeme.to_string(),
+                        variant_place: variant.place,
+                        bindings: bindings.len(),
+                    });
+                    let index = chunk.patterns.len() as u32 - 1;
+
+                    self.emit(Op::Pattern(index), ty.place);
+                    let to_next = self.emit(Op::JumpIfFalse(0), ty.place);
+                    self.emit(Op::Pop, ty.place);
+
+                    self.begin_scope();
+                    self.emit(Op::Destructure, ty.place);
+
+                    for binding in bindings {
+                        self.add_local(&binding.lexeme);
+                    }
+
+                    self.statement(arm);
+                    self.end_scope();
+
+                    to_end.push(self.emit(Op::Jump(0), keyword.place));
+                    self.patch(to_next);
+                    self.emit(Op::Pop, ty.place);
+                }
+            }
+        }
+
+        for jump in to_end {
+            self.patch(jump);
+        }
+
+        self.end_scope();
+    }
+
+    fn call(&mut self, call: ExprId, callee: ExprId, paren: TokenId, args: ExprList, tail: bool) {
+        let ast = self.ast;
+        self.expression(callee);
+
+        for arg in &ast[args] {
+            self.expression(*arg);
+        }
+
+        let chunk = &mut self.current().proto.chunk;
+        chunk.calls.push(CallSite {
+            args: ast[args].iter().map(|arg| ast.place(*arg)).collect(),
+            place: ast.place(call),
+        });
+        let index = chunk.calls.len() as u32 - 1;
+
+        let op = if tail {
+            Op::TailCall(index)
+        } else {
+            Op::Call(index)
+        };
+        self.emit(op, ast[paren].place);
+    }
+
+    fn expression(&mut self, expr: ExprId) {
+        let ast = self.ast;
+
+        match ast[expr] {
+            Expr::Literal(value) => {
+                let constant = self.constant(ast[value].clone());
+                self.emit(Op::Constant(constant), ast.place(expr));
+            }
+            Expr::Grouping(expr) => self.expression(expr),
+            Expr::Unary(op, rhs) => {
+                self.expression(rhs);
+
+                let op = &ast[op];
+                match op._type {
+                    TokenType::Minus => self.emit(Op::Negate, op.place),
+                    _ => self.emit(Op::Not, op.place),
+                };
+            }
+            Expr::Binary(lhs, op, rhs) => {
+                self.expression(lhs);
+                self.expression(rhs);
+
+                let op = &ast[op];
+                let op_code = match op._type {
+                    TokenType::Minus => Op::Subtract,
+                    TokenType::Plus => Op::Add,
+                    TokenType::Slash => Op::Divide,
+                    TokenType::Star => Op::Multiply,
+                    TokenType::Percent => Op::Remainder,
+                    TokenType::Greater => Op::Greater,
+                    TokenType::GreaterEqual => Op::GreaterEqual,
+                    TokenType::Less => Op::Less,
+                    TokenType::LessEqual => Op::LessEqual,
+                    TokenType::EqualEqual => Op::Equal,
+                    _ => Op::NotEqual,
+                };
+
+                self.emit(op_code, op.place);
+            }
+            Expr::Logical(lhs, op, rhs) => {
+                self.expression(lhs);
+
+                let op = &ast[op];
+                let jump = if op._type == TokenType::Or {
+                    Op::JumpIfTrue(0)
+                } else {
+                    Op::JumpIfFalse(0)
+                };
+
+                let to_end = self.emit(jump, op.place);
+                self.emit(Op::Pop, op.place);
+                self.expression(rhs);
+                self.patch(to_end);
+            }
+            Expr::Variable(name, _) => {
+                let name = &ast[name];
+                let (get, _) = self.variable(&name.lexeme);
+                self.emit(get, name.place);
+            }
+            Expr::Assign(name, _, value) => {
+                self.expression(value);
+
+                let name = &ast[name];
+                let (_, set) = self.variable(&name.lexeme);
+                self.emit(set, name.place);
+            }
+            Expr::Call(callee, paren, args) => self.call(expr, callee, paren, args, false),
+            Expr::Tuple(elements) => {
+                for element in &ast[elements] {
+                    self.expression(*element);
+                }
+
+                self.emit(Op::Tuple(elements.len() as u32), ast.place(expr));
+            }
+            Expr::Get(object, name) => {
+                self.expression(object);
+
+                let name = &ast[name];
+                let constant = self.constant(Value::String(Rc::new(name.lexeme.to_string())));
+                self.emit(Op::Get(constant), name.place);
+            }
+            // The resolver rejects them, there are no classes
+            Expr::This(_) | Expr::Super(..) => {
+                self.emit(Op::Nil, ast.place(expr));
+            }
+        }
+    }
+}
@@ -1,56 +1,102 @@
-use crate::{errors::CompileError, expr::Value, token::Token};
+use crate::{
+    errors::CompileError,
+    expr::{Slot, Value},
+    symbol::Symbol,
+    token::Token,
+};
 
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
+/// The variables of a scope. Locals live in `slots`, in the order they are
+/// declared, which is the index the resolver gave them. Only the outermost
+/// environment looks variables up by name, globals can be used before they
+/// are declared and redefined.
 #[derive(Default, Clone, Debug)]
 pub struct Environment {
-    // TODO try making this a <&str, Value>
-    values: HashMap<String, Value>,
-    enclosing: Option<Rc<RefCell<Environment>>>,
+    pub(crate) slots: Vec<Value>,
+    pub(crate) globals: HashMap<Symbol, Value>,
+    pub(crate) enclosing: Option<Rc<RefCell<Environment>>>,
 }
 
 impl Environment {
     pub fn from(enclosing: &Rc<RefCell<Environment>>) -> Self {
         Self {
+            slots: Vec::new(),
+            globals: HashMap::new(),
             enclosing: Some(enclosing.clone()),
-            values: HashMap::new(),
         }
     }
 
     pub fn define(&mut self, name: &Token, value: Value) {
-        self.values.insert(name.lexeme.clone(), value);
+        if self.enclosing.is_some() {
+            self.slots.push(value);
+        } else {
+            self.globals.insert(name.lexeme.clone(), value);
+        }
     }
 
-    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), CompileError> {
-        if let Some(e) = self.values.get_mut(&name.lexeme) {
-            *e = value;
-            return Ok(());
+    /// Every value the environment holds, locals first.
+    pub(crate) fn values(&self) -> impl Iterator<Item = &Value> {
+        self.slots.iter().chain(self.globals.values())
+    }
+
+    /// The local at `slot`, counting the scopes up from `env`.
+    pub fn get_at(env: &Rc<RefCell<Environment>>, slot: Slot) -> Value {
+        let env = env.borrow();
+
+        match slot.depth {
+            0 => env.slots[slot.index].clone(),
+            depth => Environment::get_at(
+                env.enclosing
+                    .as_ref()
+                    .expect("the resolver computed a depth deeper than the scopes"),
+                Slot {
+                    depth: depth - 1,
+                    ..slot
+                },
+            ),
         }
+    }
 
-        // Recurse down the scopes
-        if let Some(enclosing) = self.enclosing.as_mut() {
-            return enclosing.borrow_mut().assign(name, value);
+    pub fn assign_at(env: &Rc<RefCell<Environment>>, slot: Slot, value: Value) {
+        if slot.depth == 0 {
+            env.borrow_mut().slots[slot.index] = value;
+            return;
         }
 
-        Err(CompileError::Interpreter(
-            name.place,
-            format!("Undefined variable: {}.", name.lexeme),
-        ))
+        let env = env.borrow();
+        Environment::assign_at(
+            env.enclosing
+                .as_ref()
+                .expect("the resolver computed a depth deeper than the scopes"),
+            Slot {
+                depth: slot.depth - 1,
+                ..slot
+            },
+            value,
+        );
     }
 
-    pub fn get(&self, name: &Token) -> Result<Value, CompileError> {
-        if let Some(value) = self.values.get(&name.lexeme) {
-            return Ok(value.clone());
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), CompileError> {
+        match self.globals.get_mut(&name.lexeme) {
+            Some(e) => {
+                *e = value;
+                Ok(())
+            }
+            None => Err(CompileError::Interpreter(
+                name.place,
+                format!("Undefined variable: {}.", name.lexeme),
+            )),
         }
+    }
 
-        // Recurse down the scopes
-        if let Some(enclosing) = &self.enclosing {
-            return enclosing.borrow_mut().get(name);
+    pub fn get(&self, name: &Token) -> Result<Value, CompileError> {
+        match self.globals.get(&name.lexeme) {
+            Some(value) => Ok(value.clone()),
+            None => Err(CompileError::Interpreter(
+                name.place,
+                format!("Undefined variable or function: {}.", name.lexeme),
+            )),
         }
-
-        Err(CompileError::Interpreter(
-            name.place,
-            format!("Undefined variable or function: {}.", name.lexeme),
-        ))
     }
 }
@@ -1,4 +1,7 @@
-use crate::expr::Value;
+use crate::{
+    budget::Interrupt, expr::Value, gc::OutOfMemory, interpreter::TailCall, lints::Lint,
+    types::Type,
+};
 
 use codespan_reporting::{
     diagnostic::{Diagnostic, Label},
@@ -14,8 +17,20 @@ use codespan_reporting::{
 pub enum CompileError {
     Parser((usize, usize), String),
     Scanner((usize, usize), String),
+    Resolver((usize, usize), String),
     Interpreter((usize, usize), String),
+    /// The script ran out of its [`Budget`](crate::budget::Budget) while
+    /// running the code at the span
+    Interrupted((usize, usize), Interrupt),
+    /// The code at the span needed more than the memory limit of the script
+    OutOfMemory((usize, usize), OutOfMemory),
+    /// Valid code that is probably wrong, only reported when its lint is on
+    Warning((usize, usize), Lint, String),
+    /// The expected type with the annotation that requires it, and the type
+    /// that was found instead with the code that produced it.
+    TypeMismatch(((usize, usize), Type), ((usize, usize), Type)),
     Return(Value),
+    TailCall(Box<TailCall>),
 }
 
 pub fn error(file_name: &str, source: &str, errors: &[CompileError]) {
@@ -32,13 +47,60 @@ pub fn error(file_name: &str, source: &str, errors: &[CompileError]) {
                 .with_message(format!("Error while parsing: {}", msg))
                 .with_labels(vec![Label::primary((), span.0..span.1).with_message("here")]),
 
+            CompileError::Resolver(span, msg) => Diagnostic::error()
+                .with_message(msg)
+                .with_labels(vec![Label::primary((), span.0..span.1)]),
+
             CompileError::Interpreter(span, msg) => Diagnostic::error()
                 .with_message(format!("Runtime error: {}", msg))
                 .with_labels(vec![Label::primary((), span.0..span.1)]),
 
-            CompileError::Return(value) => {
-                println!("{}", value);
-                std::process::exit(0);
+            CompileError::Interrupted(span, interrupt) => Diagnostic::error()
+                .with_message(match interrupt {
+                    Interrupt::Steps(steps) => {
+                        format!("Interrupted: the script took more than {} steps", steps)
+                    }
+                    Interrupt::Timeout(timeout) => format!(
+                        "Interrupted: the script ran for more than {} ms",
+                        timeout.as_millis()
+                    ),
+                })
+                .with_labels(vec![
+                    Label::primary((), span.0..span.1).with_message("it was running this")
+                ]),
+
+            CompileError::OutOfMemory(span, OutOfMemory { limit }) => Diagnostic::error()
+                .with_message(format!(
+                    "Memory limit exceeded, the script needs more than {} bytes.",
+                    limit
+                ))
+                .with_labels(vec![Label::primary((), span.0..span.1)]),
+
+            CompileError::Warning(span, lint, msg) => Diagnostic::warning()
+                .with_message(msg)
+                .with_labels(vec![Label::primary((), span.0..span.1)])
+                .with_notes(vec![format!(
+                    "silence it with `// lux-allow({})`",
+                    lint.name()
+                )]),
+
+            CompileError::TypeMismatch((expected_span, expected), (found_span, found)) => {
+                Diagnostic::error()
+                    .with_message(format!(
+                        "Type mismatch: expected {}, found {}",
+                        expected, found
+                    ))
+                    .with_labels(vec![
+                        Label::primary((), found_span.0..found_span.1)
+                            .with_message(format!("this is {}", found)),
+                        Label::secondary((), expected_span.0..expected_span.1)
+                            .with_message(format!("expected {} because of this", expected)),
+                    ])
+            }
+
+            // The resolver rejects returns outside of functions
+            CompileError::Return(_) | CompileError::TailCall(_) => {
+                Diagnostic::error().with_message("Can't return from top-level code.")
             }
         };
 
@@ -0,0 +1,139 @@
+use crate::{
+    errors::CompileError,
+    lints::Lint,
+    statements::{Pattern, Statement},
+};
+
+use std::collections::HashMap;
+
+/// Warns about `match` statements that miss variants of the enum they branch
+/// over, and about arms that can never run.
+pub fn check(statements: &[Statement]) -> Vec<CompileError> {
+    let mut enums = HashMap::new();
+    collect_enums(statements, &mut enums);
+
+    let mut warnings = Vec::new();
+    check_statements(statements, &enums, &mut warnings);
+
+    warnings
+}
+
+fn collect_enums<'s>(statements: &'s [Statement], enums: &mut HashMap<&'s str, Vec<&'s str>>) {
+    for stmt in statements {
+        match stmt {
+            Statement::Enum(decl) => {
+                let variants = decl.variants.iter().map(|(v, _)| &*v.lexeme).collect();
+                enums.insert(&decl.name.lexeme, variants);
+            }
+            Statement::Block(body) => collect_enums(body, enums),
+            Statement::Function(function) => collect_enums(&function.body, enums),
+            Statement::If(_, then_branch, else_branch) => {
+                collect_enums(std::slice::from_ref(then_branch), enums);
+                if let Some(else_branch) = else_branch {
+                    collect_enums(std::slice::from_ref(else_branch), enums);
+                }
+            }
+            Statement::While(_, body) => collect_enums(std::slice::from_ref(body), enums),
+            Statement::Match(_, _, arms) => {
+                for (_, arm) in arms {
+                    collect_enums(std::slice::from_ref(arm), enums);
+                }
+            }
+            Statement::Print(_)
+            | Statement::Expresion(_)
+            | Statement::Var(_)
+            | Statement::Unpack(..)
+            | Statement::Return(..) => {}
+        }
+    }
+}
+
+fn check_statements(
+    statements: &[Statement],
+    enums: &HashMap<&str, Vec<&str>>,
+    warnings: &mut Vec<CompileError>,
+) {
+    for stmt in statements {
+        check_statement(stmt, enums, warnings);
+    }
+}
+
+fn check_statement(
+    stmt: &Statement,
+    enums: &HashMap<&str, Vec<&str>>,
+    warnings: &mut Vec<CompileError>,
+) {
+    match stmt {
+        Statement::Block(body) => check_statements(body, enums, warnings),
+        Statement::Function(function) => check_statements(&function.body, enums, warnings),
+        Statement::If(_, then_branch, else_branch) => {
+            check_statement(then_branch, enums, warnings);
+            if let Some(else_branch) = else_branch {
+                check_statement(else_branch, enums, warnings);
+            }
+        }
+        Statement::While(_, body) => check_statement(body, enums, warnings),
+        Statement::Match(keyword, _, arms) => {
+            let mut has_wildcard = false;
+            let mut mixed = false;
+            let mut ty = None;
+            let mut covered = Vec::new();
+
+            for (pattern, arm) in arms {
+                check_statement(arm, enums, warnings);
+
+                if has_wildcard {
+                    let place = match pattern {
+                        Pattern::Variant(ty, _, variant, _) => (ty.place.0, variant.place.1),
+                        Pattern::Wildcard(token) => token.place,
+                    };
+
+                    warnings.push(CompileError::Warning(
+                        place,
+                        Lint::Unreachable,
+                        "Unreachable match arm, a previous `else` arm matches everything.".into(),
+                    ));
+                    continue;
+                }
+
+                match pattern {
+                    Pattern::Wildcard(_) => has_wildcard = true,
+                    Pattern::Variant(name, _, variant, _) => {
+                        // Arms over different enums can only be checked at runtime
+                        if *ty.get_or_insert(&name.lexeme) == &name.lexeme {
+                            covered.push(&*variant.lexeme);
+                        } else {
+                            mixed = true;
+                        }
+                    }
+                }
+            }
+
+            if has_wildcard || mixed {
+                return;
+            }
+
+            let ty = match ty {
+                Some(ty) => ty,
+                None => return,
+            };
+
+            if let Some(variants) = enums.get(ty.as_str()) {
+                let missing: Vec<_> = variants
+                    .iter()
+                    .filter(|v| !covered.contains(v))
+                    .map(|v| format!("{}.{}", ty, v))
+                    .collect();
+
+                if !missing.is_empty() {
+                    warnings.push(CompileError::Warning(
+                        keyword.place,
+                        Lint::NonExhaustive,
+                        format!("Non-exhaustive match, missing: {}.", missing.join(", ")),
+                    ));
+                }
+            }
+        }
+        _ => {}
+    }
+}
@@ -1,34 +1,350 @@
-use crate::{environment::Environment, errors::CompileError, statements::Statement, token::Token};
+use crate::{
+    environment::Environment, natives::Context, statements::FunctionDecl, token::Token,
+    types::Type, vm::Closure,
+};
 
 use std::{
     cell::RefCell,
+    cmp::Ordering,
     fmt::{Debug, Display, Formatter},
+    hash::{Hash, Hasher},
+    mem::size_of,
+    ops::{Index, IndexMut},
     rc::Rc,
 };
 
-#[derive(Debug, Clone, PartialEq)]
+/// An expression, its operands are other nodes of the [`Ast`] it belongs to
+/// and its tokens live in the token table of that same tree.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Expr {
-    Binary(Box<Expr>, Token, Box<Expr>),
-    Logical(Box<Expr>, Token, Box<Expr>),
-    Unary(Token, Box<Expr>),
-    Literal(Value),
-    Grouping(Box<Expr>),
-    Variable(Token),
-    Assign(Token, Box<Expr>),
-    Call(Box<Expr>, Token, Vec<Expr>),
+    Binary(ExprId, TokenId, ExprId),
+    Logical(ExprId, TokenId, ExprId),
+    Unary(TokenId, ExprId),
+    Literal(LiteralId),
+    Grouping(ExprId),
+    /// Where the variable lives is set by the resolver, `None` means it is
+    /// a global.
+    Variable(TokenId, Option<Slot>),
+    Assign(TokenId, Option<Slot>, ExprId),
+    Call(ExprId, TokenId, ExprList),
+    Get(ExprId, TokenId),
+    Tuple(ExprList),
+    This(TokenId),
+    /// The `super` keyword and the method name
+    Super(TokenId, TokenId),
+}
+
+/// Where a local variable lives: how many scopes up from its use it is
+/// declared, and its position among the variables of that scope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slot {
+    pub depth: usize,
+    pub index: usize,
+}
+
+/// An expression of an [`Ast`]. Ids are never reused, so passes and tools
+/// can keep what they know about a node in their own tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExprId(u32);
+
+/// A token of an [`Ast`], in the order the parser consumed them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TokenId(u32);
+
+/// The value of a literal in an [`Ast`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LiteralId(u32);
+
+/// Consecutive expressions of an [`Ast`], like the arguments of a call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExprList {
+    start: u32,
+    len: u32,
+}
+
+impl ExprId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl TokenId {
+    pub(crate) fn new(index: usize) -> Self {
+        TokenId(index as u32)
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl ExprList {
+    pub fn len(self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Every expression of a program, stored in arrays instead of boxed one by
+/// one. Nodes refer to each other and to their tokens by index, and the
+/// span of each node is kept on the side so finding it doesn't walk the
+/// subtree.
+///
+/// Nodes are only added, the ids handed out stay valid as long as the tree.
+/// Functions keep the tree they are declared in, so every program, or line
+/// of the REPL, has its own.
+///
+/// ```
+/// use lux::expr::{Ast, Expr, Value};
+///
+/// let mut ast = Ast::default();
+/// let one = ast.literal(Value::Number(1.0), (0, 1));
+/// let tuple = ast.list([one, one]);
+/// let pair = ast.push(Expr::Tuple(tuple));
+///
+/// assert_eq!(ast[tuple], [one, one]);
+/// assert_eq!(ast.place(pair), (0, 1));
+/// ```
+#[derive(Debug, Default)]
+pub struct Ast {
+    exprs: Vec<Expr>,
+    /// The span of source code of every expression, by its id
+    spans: Vec<(usize, usize)>,
+    pub(crate) tokens: Vec<Token>,
+    literals: Vec<Value>,
+    lists: Vec<ExprId>,
+}
+
+impl Ast {
+    /// Adds `expr`, it spans from the start of its first token or operand to
+    /// the end of its last one. Literals are added with [`Ast::literal`].
+    pub fn push(&mut self, expr: Expr) -> ExprId {
+        let place = match expr {
+            Expr::Binary(lhs, _, rhs) | Expr::Logical(lhs, _, rhs) => {
+                (self.place(lhs).0, self.place(rhs).1)
+            }
+            Expr::Unary(op, rhs) => (self[op].place.0, self.place(rhs).1),
+            Expr::Literal(_) => unreachable!("literals are added with their span"),
+            Expr::Grouping(expr) => self.place(expr),
+            Expr::Variable(name, _) => self[name].place,
+            Expr::Assign(name, _, value) => (self[name].place.0, self.place(value).1),
+            Expr::Call(callee, paren, _) => (self.place(callee).0, self[paren].place.1),
+            Expr::Get(object, name) => (self.place(object).0, self[name].place.1),
+            Expr::Tuple(elements) => {
+                let elements = &self[elements];
+                (
+                    self.place(elements[0]).0,
+                    self.place(elements[elements.len() - 1]).1,
+                )
+            }
+            Expr::This(keyword) => self[keyword].place,
+            Expr::Super(keyword, method) => (self[keyword].place.0, self[method].place.1),
+        };
+
+        self.insert(expr, place)
+    }
+
+    /// Adds a literal spanning `place`.
+    pub fn literal(&mut self, value: Value, place: (usize, usize)) -> ExprId {
+        let literal = LiteralId(self.literals.len() as u32);
+        self.literals.push(value);
+        self.insert(Expr::Literal(literal), place)
+    }
+
+    /// Turns `id` into a literal, it keeps its span.
+    pub fn fold(&mut self, id: ExprId, value: Value) {
+        let literal = LiteralId(self.literals.len() as u32);
+        self.literals.push(value);
+        self[id] = Expr::Literal(literal);
+    }
+
+    pub fn list(&mut self, ids: impl IntoIterator<Item = ExprId>) -> ExprList {
+        let start = self.lists.len() as u32;
+        self.lists.extend(ids);
+
+        ExprList {
+            start,
+            len: self.lists.len() as u32 - start,
+        }
+    }
+
+    pub fn token(&mut self, token: Token) -> TokenId {
+        self.tokens.push(token);
+        TokenId::new(self.tokens.len() - 1)
+    }
+
+    /// The span of source code `id` was parsed from.
+    pub fn place(&self, id: ExprId) -> (usize, usize) {
+        self.spans[id.index()]
+    }
+
+    fn insert(&mut self, expr: Expr, place: (usize, usize)) -> ExprId {
+        self.exprs.push(expr);
+        self.spans.push(place);
+        ExprId(self.exprs.len() as u32 - 1)
+    }
+}
+
+impl Index<ExprId> for Ast {
+    type Output = Expr;
+
+    fn index(&self, id: ExprId) -> &Expr {
+        &self.exprs[id.index()]
+    }
+}
+
+impl IndexMut<ExprId> for Ast {
+    fn index_mut(&mut self, id: ExprId) -> &mut Expr {
+        &mut self.exprs[id.index()]
+    }
+}
+
+impl Index<TokenId> for Ast {
+    type Output = Token;
+
+    fn index(&self, id: TokenId) -> &Token {
+        &self.tokens[id.index()]
+    }
+}
+
+impl Index<LiteralId> for Ast {
+    type Output = Value;
+
+    fn index(&self, id: LiteralId) -> &Value {
+        &self.literals[id.0 as usize]
+    }
+}
+
+impl Index<ExprList> for Ast {
+    type Output = [ExprId];
+
+    fn index(&self, list: ExprList) -> &[ExprId] {
+        &self.lists[list.start as usize..][..list.len()]
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+impl IndexMut<ExprList> for Ast {
+    fn index_mut(&mut self, list: ExprList) -> &mut [ExprId] {
+        &mut self.lists[list.start as usize..][..list.len()]
+    }
+}
+
+/// Equality (`==`) follows these rules:
+///
+/// - Values of different kinds are never equal, `1 == "1"` and `nil == false` are `false`.
+/// - Numbers compare as IEEE 754 floats, `0 == -0` is `true` and `NaN` is not equal to anything.
+/// - Strings, booleans and `nil` compare by value.
+/// - Tuples and enum values compare structurally, enum values must also come from the same
+///   enum and variant.
+/// - Functions and enums compare by identity, see [`Value::same`].
+///
+/// Only numbers, strings and tuples of those can be ordered, see [`Value::compare`].
+///
+/// A value takes 16 bytes, anything bigger than a number lives on the heap
+/// behind a reference counted handle, shared by the copies of the value.
+#[derive(Debug, Clone)]
 pub enum Value {
-    String(String),
+    /// Immutable, copies of a string share its text
+    String(Rc<String>),
     Number(f64),
     Boolean(bool),
-    Callable(Function),
+    Callable(Rc<Function>),
+    Enum(Rc<Enum>),
+    Tagged(Rc<Tagged>),
+    Tuple(Rc<Vec<Value>>),
     Nil,
 }
 
-// TODO improve errors: report error in the correct place, not just "(0, 0)"
+const _: () = assert!(size_of::<Value>() == 16);
+
+impl PartialEq for Value {
+    fn eq(&self, o: &Self) -> bool {
+        match (self, o) {
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::Callable(a), Value::Callable(b)) => a == b,
+            (Value::Enum(a), Value::Enum(b)) => Rc::ptr_eq(a, b),
+            (Value::Tagged(a), Value::Tagged(b)) => a == b,
+            (Value::Tuple(a), Value::Tuple(b)) => a == b,
+            (Value::Nil, Value::Nil) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Consistent with `==`: equal values have the same hash. `NaN` hashes fine but,
+/// as it is not equal to itself, it can not be found again as a map key.
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+
+        match self {
+            Value::String(s) => s.hash(state),
+            // `0` and `-0` are equal so they must hash the same
+            Value::Number(n) if *n == 0.0 => 0u64.hash(state),
+            Value::Number(n) => n.to_bits().hash(state),
+            Value::Boolean(b) => b.hash(state),
+            Value::Callable(f) => f.hash(state),
+            Value::Enum(e) => Rc::as_ptr(e).hash(state),
+            Value::Tagged(t) => {
+                Rc::as_ptr(&t.ty).hash(state);
+                t.variant.hash(state);
+                t.payload.hash(state);
+            }
+            Value::Tuple(values) => values.hash(state),
+            Value::Nil => {}
+        }
+    }
+}
+
 impl Value {
+    /// The ordering used by `<`, `<=`, `>` and `>=`: numbers compare numerically,
+    /// strings lexicographically and tuples element by element with the same
+    /// rules. Only `NaN` is unordered, anything else is an error.
+    pub fn compare(&self, o: &Self) -> Result<Option<Ordering>, String> {
+        match (self, o) {
+            (Value::Number(a), Value::Number(b)) => Ok(a.partial_cmp(b)),
+            (Value::String(a), Value::String(b)) => Ok(Some(a.cmp(b))),
+            (Value::Tuple(a), Value::Tuple(b)) => {
+                for (a, b) in a.iter().zip(b.iter()) {
+                    match a.compare(b)? {
+                        Some(Ordering::Equal) => {}
+                        other => return Ok(other),
+                    }
+                }
+
+                Ok(Some(a.len().cmp(&b.len())))
+            }
+            _ => Err(format!("Can't compare {} and {}.", self.kind(), o.kind())),
+        }
+    }
+
+    /// The name of the kind of value for error messages.
+    pub fn kind(&self) -> String {
+        match self {
+            Value::Enum(e) => format!("enum {}", e.name),
+            other => Type::of(other).to_string(),
+        }
+    }
+
+    /// Identity, what the `same(a, b)` native returns. Like `==` except that
+    /// `NaN` is the same as itself, `0` is not the same as `-0` and tuples and
+    /// enum values must be the same allocation, like functions.
+    pub fn same(&self, o: &Self) -> bool {
+        match (self, o) {
+            (Value::Number(a), Value::Number(b)) => {
+                a.to_bits() == b.to_bits() || (a.is_nan() && b.is_nan())
+            }
+            (Value::Tuple(a), Value::Tuple(b)) => Rc::ptr_eq(a, b),
+            (Value::Tagged(a), Value::Tagged(b)) => Rc::ptr_eq(a, b),
+            _ => self == o,
+        }
+    }
+
     pub fn is_truthy(&self) -> bool {
         match self {
             Value::Boolean(b) => *b,
@@ -37,82 +353,66 @@ impl Value {
         }
     }
 
-    pub fn neg(self) -> Result<Value, CompileError> {
+    pub(crate) fn neg(self, policy: NumericPolicy) -> Result<Value, String> {
         let val = match self {
             Value::Number(v) => Value::Number(-v),
-            // TODO auto cast numbers?
-            Value::String(s) => match s.parse::<f64>() {
+            Value::String(s) if policy == NumericPolicy::Permissive => match s.parse::<f64>() {
                 Ok(v) => Value::Number(-v),
-                Err(_) => return Err(CompileError::Interpreter((0, 0), "Not a number".into())),
+                Err(_) => return Err("Not a number".into()),
             },
-            _ => {
-                return Err(CompileError::Interpreter(
-                    (0, 0),
-                    "No - for the given value.".into(),
-                ))
-            }
+            Value::String(_) => return Err("Can't negate a string, it is not a number.".into()),
+            _ => return Err("No - for the given value.".into()),
         };
 
         Ok(val)
     }
 
-    pub fn mul(self, rhs: Self) -> Result<Value, CompileError> {
-        let err = Err(CompileError::Interpreter(
-            (0, 0),
-            "No Mul for the given value".into(),
-        ));
-
-        match self {
-            Value::Number(lhs) => match rhs {
-                Value::Number(rhs) => Ok(Value::Number(lhs * rhs)),
-                _ => err,
-            },
-            _ => err,
+    pub(crate) fn mul(self, rhs: Self, policy: NumericPolicy) -> Result<Value, String> {
+        match (self, rhs) {
+            (Value::Number(lhs), Value::Number(rhs)) => policy.number(lhs, rhs, lhs * rhs),
+            _ => Err("No Mul for the given value".into()),
         }
     }
 
-    pub fn div(self, rhs: Self) -> Result<Value, CompileError> {
-        let err = Err(CompileError::Interpreter(
-            (0, 0),
-            "No Div for the given value".into(),
-        ));
-
-        // TODO handle x/0
-
-        match self {
-            Value::Number(lhs) => match rhs {
-                Value::Number(rhs) => Ok(Value::Number(lhs / rhs)),
-                _ => err,
-            },
-            _ => err,
+    pub(crate) fn div(self, rhs: Self, policy: NumericPolicy) -> Result<Value, String> {
+        match (self, rhs) {
+            (Value::Number(lhs), Value::Number(rhs)) => {
+                policy.divisor(rhs)?;
+                policy.number(lhs, rhs, lhs / rhs)
+            }
+            _ => Err("No Div for the given value".into()),
         }
     }
 
-    pub fn sub(self, rhs: Self) -> Result<Value, CompileError> {
-        let err = Err(CompileError::Interpreter(
-            (0, 0),
-            "No Sub for the given value".into(),
-        ));
+    pub(crate) fn rem(self, rhs: Self, policy: NumericPolicy) -> Result<Value, String> {
+        match (self, rhs) {
+            (Value::Number(lhs), Value::Number(rhs)) => {
+                policy.divisor(rhs)?;
+                policy.number(lhs, rhs, lhs % rhs)
+            }
+            _ => Err("No Rem for the given value".into()),
+        }
+    }
 
-        match self {
-            Value::Number(lhs) => match rhs {
-                Value::Number(rhs) => Ok(Value::Number(lhs - rhs)),
-                _ => err,
-            },
-            _ => err,
+    pub(crate) fn sub(self, rhs: Self, policy: NumericPolicy) -> Result<Value, String> {
+        match (self, rhs) {
+            (Value::Number(lhs), Value::Number(rhs)) => policy.number(lhs, rhs, lhs - rhs),
+            _ => Err("No Sub for the given value".into()),
         }
     }
 
-    pub fn add(self, rhs: Self) -> Result<Value, CompileError> {
+    pub(crate) fn add(self, rhs: Self, policy: NumericPolicy) -> Result<Value, String> {
         let error_msg = match self {
             Value::Number(lhs) => match rhs {
-                Value::Number(rhs) => return Ok(Value::Number(lhs + rhs)),
+                Value::Number(rhs) => return policy.number(lhs, rhs, lhs + rhs),
                 Value::String(_) => "No Add for Number and String",
                 _ => "No Add for the given values",
             },
 
             Value::String(lhs) => match rhs {
-                Value::String(rhs) => return Ok(Value::String(lhs + &rhs)),
+                Value::String(rhs) => {
+                    return Ok(Value::String(Rc::new([lhs.as_str(), &rhs].concat())))
+                }
                 Value::Number(_) => "No Add for String and Number",
                 _ => "No Add for the given values",
             },
@@ -120,7 +420,38 @@ impl Value {
             _ => "No Add for the given values",
         };
 
-        Err(CompileError::Interpreter((0, 0), error_msg.into()))
+        Err(error_msg.into())
+    }
+}
+
+/// How arithmetic deals with questionable operations.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum NumericPolicy {
+    /// Strings are coerced to numbers by `-`, and `x / 0` follows IEEE 754
+    /// giving `inf` or `NaN`
+    #[default]
+    Permissive,
+    /// No coercions, and dividing by zero or producing a `NaN` out of numbers
+    /// are errors
+    Strict,
+}
+
+impl NumericPolicy {
+    fn divisor(self, rhs: f64) -> Result<(), String> {
+        if self == NumericPolicy::Strict && rhs == 0.0 {
+            Err("Division by zero.".into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The result of an operation between `lhs` and `rhs`.
+    fn number(self, lhs: f64, rhs: f64, result: f64) -> Result<Value, String> {
+        if self == NumericPolicy::Strict && result.is_nan() && !lhs.is_nan() && !rhs.is_nan() {
+            Err(format!("Operation on {} and {} produced NaN.", lhs, rhs))
+        } else {
+            Ok(Value::Number(result))
+        }
     }
 }
 
@@ -131,42 +462,165 @@ impl Display for Value {
             Value::Number(n) => write!(f, "{}", n),
             Value::Boolean(b) => write!(f, "{}", b),
             Value::Callable(c) => write!(f, "{:?}", c),
+            Value::Enum(e) => write!(f, "<enum {}>", e.name),
+            Value::Tagged(t) => {
+                write!(f, "{}.{}", t.ty.name, t.ty.variants[t.variant].0)?;
+
+                if !t.payload.is_empty() {
+                    write!(f, "(")?;
+                    for (i, value) in t.payload.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        write_nested(f, value)?;
+                    }
+                    write!(f, ")")?;
+                }
+
+                Ok(())
+            }
+            Value::Tuple(values) => {
+                write!(f, "(")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_nested(f, value)?;
+                }
+                // A trailing comma tells a one element tuple from a grouping
+                if values.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
             Value::Nil => write!(f, "nil"),
         }
     }
 }
 
+/// Values inside of other values show strings quoted, so `Pair.Of("1", 1)` is
+/// not ambiguous.
+fn write_nested(f: &mut Formatter<'_>, value: &Value) -> std::fmt::Result {
+    match value {
+        Value::String(s) => write!(f, "{:?}", s),
+        other => write!(f, "{}", other),
+    }
+}
+
+/// An enum type, created every time its declaration is executed.
+#[derive(Debug)]
+pub struct Enum {
+    pub name: String,
+    /// The name and payload size of every variant
+    pub variants: Vec<(String, usize)>,
+}
+
+impl Enum {
+    pub fn variant(&self, name: &str) -> Option<usize> {
+        self.variants.iter().position(|(v, _)| v == name)
+    }
+}
+
+// Two enums are the same only if they come from the same declaration.
+impl PartialEq for Enum {
+    fn eq(&self, o: &Self) -> bool {
+        std::ptr::eq(self, o)
+    }
+}
+
+/// A value of an enum type: the variant tag and its payload.
+#[derive(Debug, Clone)]
+pub struct Tagged {
+    pub ty: Rc<Enum>,
+    pub variant: usize,
+    pub payload: Vec<Value>,
+}
+
+pub type NativeFn = fn(&mut Context<'_>, &[Value]) -> Result<Value, String>;
+
 #[derive(Clone)]
 pub enum Function {
     Native {
+        name: &'static str,
         arity: usize,
-        body: Box<fn(&[Value]) -> Value>,
+        body: Box<NativeFn>,
     },
     User {
-        name: Token,
-        params: Vec<Token>,
-        body: Vec<Statement>,
+        declaration: Rc<FunctionDecl>,
         closure: Rc<RefCell<Environment>>,
+        /// The tree the expressions of the declaration are in
+        ast: Rc<Ast>,
     },
+    /// The constructor of an enum variant that carries a payload
+    Constructor { ty: Rc<Enum>, variant: usize },
+    /// A function compiled to bytecode
+    Closure(Rc<Closure>),
 }
 
 impl Debug for Function {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Function::Native { .. } => write!(f, "<native function>"),
-            Function::User { name, .. } => write!(f, "<fn {}>", name.lexeme),
+            Function::User { declaration, .. } => write!(f, "<fn {}>", declaration.name.lexeme),
+            Function::Constructor { ty, variant } => {
+                write!(f, "<fn {}.{}>", ty.name, ty.variants[*variant].0)
+            }
+            Function::Closure(closure) => write!(f, "<fn {}>", closure.proto.name),
         }
     }
 }
 
-impl PartialOrd for Function {
-    fn partial_cmp(&self, _o: &Self) -> Option<std::cmp::Ordering> {
-        None
+impl PartialEq for Tagged {
+    fn eq(&self, o: &Self) -> bool {
+        Rc::ptr_eq(&self.ty, &o.ty) && self.variant == o.variant && self.payload == o.payload
     }
 }
 
+/// Functions are equal only to themselves: natives with the same name, user
+/// functions created by the same execution of their declaration and
+/// constructors of the same variant.
 impl PartialEq for Function {
-    fn eq(&self, _o: &Self) -> bool {
-        false
+    fn eq(&self, o: &Self) -> bool {
+        match (self, o) {
+            (Function::Native { name: a, .. }, Function::Native { name: b, .. }) => a == b,
+            (
+                Function::User {
+                    declaration: a,
+                    closure: a_closure,
+                    ..
+                },
+                Function::User {
+                    declaration: b,
+                    closure: b_closure,
+                    ..
+                },
+            ) => Rc::ptr_eq(a, b) && Rc::ptr_eq(a_closure, b_closure),
+            (
+                Function::Constructor {
+                    ty: a,
+                    variant: a_variant,
+                },
+                Function::Constructor {
+                    ty: b,
+                    variant: b_variant,
+                },
+            ) => Rc::ptr_eq(a, b) && a_variant == b_variant,
+            (Function::Closure(a), Function::Closure(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Hash for Function {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Function::Native { name, .. } => name.hash(state),
+            Function::User { declaration, .. } => Rc::as_ptr(declaration).hash(state),
+            Function::Constructor { ty, variant } => {
+                Rc::as_ptr(ty).hash(state);
+                variant.hash(state);
+            }
+            Function::Closure(closure) => Rc::as_ptr(closure).hash(state),
+        }
     }
 }
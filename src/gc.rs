@@ -0,0 +1,427 @@
+use crate::{
+    environment::Environment,
+    expr::{Function, Value},
+    vm::Upvalue,
+};
+
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    mem::{self, size_of},
+    rc::{Rc, Weak},
+};
+
+/// Collections don't run before this many objects are tracked.
+const MIN_THRESHOLD: usize = 1024;
+
+/// What a call or a new environment takes from the memory budget, about the
+/// size of an environment.
+pub const FRAME: usize = size_of::<Environment>();
+
+/// The script needs more memory than the limit, in bytes, allows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutOfMemory {
+    pub limit: usize,
+}
+
+/// Keeps track of the objects that can form reference cycles: the
+/// environments and functions of the tree walking interpreter and the
+/// upvalues of the virtual machine. A closure stored in a variable it
+/// captures keeps itself alive, collections find the objects only reachable
+/// from each other and break their cycles.
+///
+/// Objects are found by trial deletion: the references an object gets from
+/// the other tracked objects are subtracted from its reference count, what
+/// is left comes from outside (the interpreter, a value being computed) and
+/// makes it a root. Everything the roots can't reach is garbage.
+#[derive(Debug)]
+pub struct Heap {
+    environments: Vec<Weak<RefCell<Environment>>>,
+    functions: Vec<Weak<Function>>,
+    upvalues: Vec<Weak<RefCell<Upvalue>>>,
+    /// Tracked objects that start a collection
+    threshold: usize,
+    stats: Stats,
+    /// The memory budget in bytes
+    limit: Option<usize>,
+    /// Bytes in use, as of the last measure plus what was allocated since
+    in_use: usize,
+}
+
+/// What the collector has done so far.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub collections: usize,
+    /// Objects freed by collections, the ones freed by reference counting
+    /// aren't counted
+    pub reclaimed: usize,
+    /// Tracked objects alive after the last collection
+    pub live: usize,
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self {
+            environments: Vec::new(),
+            functions: Vec::new(),
+            upvalues: Vec::new(),
+            threshold: MIN_THRESHOLD,
+            stats: Stats::default(),
+            limit: None,
+            in_use: 0,
+        }
+    }
+}
+
+/// A tracked object during a collection.
+enum Node {
+    Environment(Rc<RefCell<Environment>>),
+    Function(Rc<Function>),
+    Upvalue(Rc<RefCell<Upvalue>>),
+}
+
+impl Node {
+    fn address(&self) -> usize {
+        match self {
+            Node::Environment(env) => Rc::as_ptr(env) as *const () as usize,
+            Node::Function(function) => Rc::as_ptr(function) as *const () as usize,
+            Node::Upvalue(upvalue) => Rc::as_ptr(upvalue) as *const () as usize,
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Node::Environment(env) => Rc::strong_count(env),
+            Node::Function(function) => Rc::strong_count(function),
+            Node::Upvalue(upvalue) => Rc::strong_count(upvalue),
+        }
+    }
+
+    /// The tracked objects this one references, `None` if it is in use and
+    /// can't be looked at.
+    fn children(&self) -> Option<Vec<usize>> {
+        let mut children = Vec::new();
+
+        match self {
+            Node::Environment(env) => {
+                let env = env.try_borrow().ok()?;
+
+                if let Some(enclosing) = &env.enclosing {
+                    children.push(Rc::as_ptr(enclosing) as *const () as usize);
+                }
+
+                for value in env.values() {
+                    references(value, &mut children);
+                }
+            }
+            Node::Function(function) => {
+                if let Function::User { closure, .. } = &**function {
+                    children.push(Rc::as_ptr(closure) as *const () as usize);
+                }
+            }
+            Node::Upvalue(upvalue) => {
+                if let Upvalue::Closed(value) = &*upvalue.try_borrow().ok()? {
+                    references(value, &mut children);
+                }
+            }
+        }
+
+        Some(children)
+    }
+
+    /// Drops everything the object holds, the values are returned so they
+    /// are dropped once no object is borrowed. Functions can't be changed,
+    /// they are freed with the environments that hold them.
+    fn clear(&self) -> Vec<Value> {
+        match self {
+            Node::Environment(env) => {
+                let env = mem::take(&mut *env.borrow_mut());
+                env.slots
+                    .into_iter()
+                    .chain(env.globals.into_values())
+                    .collect()
+            }
+            Node::Function(_) => Vec::new(),
+            Node::Upvalue(upvalue) => match upvalue.replace(Upvalue::Closed(Value::Nil)) {
+                Upvalue::Closed(value) => vec![value],
+                Upvalue::Open(_) => Vec::new(),
+            },
+        }
+    }
+}
+
+/// Adds the tracked objects `value` references. Values shared with something
+/// else are skipped, that makes what they reference a root.
+fn references(value: &Value, found: &mut Vec<usize>) {
+    match value {
+        Value::Callable(function) => match &**function {
+            Function::User { .. } => found.push(Rc::as_ptr(function) as *const () as usize),
+            Function::Closure(closure)
+                if Rc::strong_count(function) == 1 && Rc::strong_count(closure) == 1 =>
+            {
+                for upvalue in &closure.upvalues {
+                    found.push(Rc::as_ptr(upvalue) as *const () as usize);
+                }
+            }
+            _ => {}
+        },
+        Value::Tuple(values) if Rc::strong_count(values) == 1 => {
+            for value in values.iter() {
+                references(value, found);
+            }
+        }
+        Value::Tagged(tagged) if Rc::strong_count(tagged) == 1 => {
+            for value in &tagged.payload {
+                references(value, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The bytes a new value takes besides itself.
+pub fn size(value: &Value) -> usize {
+    match value {
+        Value::String(s) => s.len(),
+        Value::Tuple(values) => values.len() * size_of::<Value>(),
+        Value::Tagged(tagged) => tagged.payload.len() * size_of::<Value>(),
+        _ => 0,
+    }
+}
+
+/// The bytes a value takes with everything it holds, shared values in `seen`
+/// are not counted again. Environments and upvalues are measured on their own.
+fn measure(value: &Value, seen: &mut HashSet<usize>) -> usize {
+    let own = size_of::<Value>() + size(value);
+
+    match value {
+        Value::String(text) if seen.insert(Rc::as_ptr(text) as *const () as usize) => own,
+        Value::String(_) => size_of::<Value>(),
+        Value::Tuple(values) if seen.insert(Rc::as_ptr(values) as *const () as usize) => {
+            own + values
+                .iter()
+                .map(|value| measure(value, seen))
+                .sum::<usize>()
+        }
+        Value::Tuple(_) => size_of::<Value>(),
+        Value::Tagged(tagged) if seen.insert(Rc::as_ptr(tagged) as *const () as usize) => {
+            own + tagged
+                .payload
+                .iter()
+                .map(|value| measure(value, seen))
+                .sum::<usize>()
+        }
+        Value::Tagged(_) => size_of::<Value>(),
+        _ => own,
+    }
+}
+
+impl Heap {
+    /// Tracks a new environment, collecting first if the heap grew enough.
+    pub fn environment(&mut self, env: Environment) -> Rc<RefCell<Environment>> {
+        self.grow();
+
+        let env = Rc::new(RefCell::new(env));
+        self.environments.push(Rc::downgrade(&env));
+        env
+    }
+
+    /// Tracks a new function, collecting first if the heap grew enough. Only
+    /// the ones with a closure can be part of a cycle.
+    pub fn function(&mut self, function: Function) -> Rc<Function> {
+        self.grow();
+
+        let function = Rc::new(function);
+        self.functions.push(Rc::downgrade(&function));
+        function
+    }
+
+    /// Tracks a new upvalue, collecting first if the heap grew enough.
+    pub fn upvalue(&mut self, upvalue: Upvalue) -> Rc<RefCell<Upvalue>> {
+        self.grow();
+
+        let upvalue = Rc::new(RefCell::new(upvalue));
+        self.upvalues.push(Rc::downgrade(&upvalue));
+        upvalue
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    pub fn set_limit(&mut self, bytes: usize) {
+        self.limit = Some(bytes);
+    }
+
+    /// Takes `bytes` from the budget. Once it seems to be exhausted, cycles
+    /// are collected and the memory in use is measured: the tracked objects
+    /// and the `roots` the caller holds. If that is still over the limit the
+    /// script is out of memory.
+    pub fn allocate<'v>(
+        &mut self,
+        bytes: usize,
+        roots: impl Iterator<Item = &'v Value>,
+    ) -> Result<(), OutOfMemory> {
+        let limit = match self.limit {
+            Some(limit) => limit,
+            None => return Ok(()),
+        };
+
+        self.in_use += bytes;
+        if self.in_use <= limit {
+            return Ok(());
+        }
+
+        self.collect();
+        self.in_use = self.measure(roots) + bytes;
+
+        if self.in_use > limit {
+            return Err(OutOfMemory { limit });
+        }
+
+        Ok(())
+    }
+
+    /// The bytes taken by the tracked objects and the `roots`.
+    fn measure<'v>(&self, roots: impl Iterator<Item = &'v Value>) -> usize {
+        let mut seen = HashSet::new();
+        let mut bytes = 0;
+
+        for env in self.environments.iter().filter_map(Weak::upgrade) {
+            bytes += size_of::<Environment>();
+
+            if let Ok(env) = env.try_borrow() {
+                bytes += env.globals.keys().map(|name| name.len()).sum::<usize>();
+
+                for value in env.values() {
+                    bytes += measure(value, &mut seen);
+                }
+            }
+        }
+
+        bytes += self
+            .functions
+            .iter()
+            .filter(|f| f.strong_count() > 0)
+            .count()
+            * size_of::<Function>();
+
+        for upvalue in self.upvalues.iter().filter_map(Weak::upgrade) {
+            bytes += size_of::<Upvalue>();
+
+            if let Ok(Upvalue::Closed(value)) = upvalue.try_borrow().as_deref() {
+                bytes += measure(value, &mut seen);
+            }
+        }
+
+        bytes + roots.map(|value| measure(value, &mut seen)).sum::<usize>()
+    }
+
+    fn tracked(&self) -> usize {
+        self.environments.len() + self.functions.len() + self.upvalues.len()
+    }
+
+    /// Stops tracking the objects freed by reference counting.
+    fn forget(&mut self) {
+        self.environments.retain(|env| env.strong_count() > 0);
+        self.functions
+            .retain(|function| function.strong_count() > 0);
+        self.upvalues.retain(|upvalue| upvalue.strong_count() > 0);
+    }
+
+    fn grow(&mut self) {
+        if self.tracked() < self.threshold {
+            return;
+        }
+
+        // Most objects are freed by reference counting, forget them first and
+        // only look for cycles if many survived
+        self.forget();
+
+        if self.tracked() * 2 >= self.threshold {
+            self.collect();
+        }
+
+        let live = self.tracked();
+        self.threshold = (live * 2).max(MIN_THRESHOLD);
+    }
+
+    /// Frees the objects that are only reachable from each other, returns
+    /// how many there were.
+    pub fn collect(&mut self) -> usize {
+        let nodes: Vec<Node> = self
+            .environments
+            .iter()
+            .filter_map(|env| env.upgrade().map(Node::Environment))
+            .chain(
+                self.functions
+                    .iter()
+                    .filter_map(|function| function.upgrade().map(Node::Function)),
+            )
+            .chain(
+                self.upvalues
+                    .iter()
+                    .filter_map(|upvalue| upvalue.upgrade().map(Node::Upvalue)),
+            )
+            .collect();
+
+        let index: HashMap<usize, usize> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.address(), i))
+            .collect();
+
+        let children: Vec<Option<Vec<usize>>> = nodes
+            .iter()
+            .map(|node| {
+                node.children().map(|children| {
+                    children
+                        .iter()
+                        .filter_map(|address| index.get(address).copied())
+                        .collect()
+                })
+            })
+            .collect();
+
+        // The reference `nodes` holds is not one of them
+        let mut external: Vec<usize> = nodes.iter().map(|node| node.strong_count() - 1).collect();
+
+        for &child in children.iter().flatten().flatten() {
+            external[child] -= 1;
+        }
+
+        // Objects in use are roots too, what they reference wasn't subtracted
+        let mut reachable = vec![false; nodes.len()];
+        let mut pending: Vec<usize> = (0..nodes.len())
+            .filter(|&i| external[i] > 0 || children[i].is_none())
+            .collect();
+
+        while let Some(i) = pending.pop() {
+            if mem::replace(&mut reachable[i], true) {
+                continue;
+            }
+
+            pending.extend(children[i].iter().flatten());
+        }
+
+        let mut garbage = Vec::new();
+
+        for (node, reachable) in nodes.iter().zip(&reachable) {
+            if !reachable {
+                garbage.extend(node.clear());
+            }
+        }
+
+        let reclaimed = reachable.iter().filter(|r| !**r).count();
+        drop(garbage);
+        drop(nodes);
+
+        self.forget();
+
+        self.stats.collections += 1;
+        self.stats.reclaimed += reclaimed;
+        self.stats.live = self.tracked();
+
+        reclaimed
+    }
+}
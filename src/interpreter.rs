@@ -1,85 +1,264 @@
 use crate::{
+    budget::Budget,
     environment::Environment,
     errors::CompileError,
-    expr::{Expr, Function, Value},
-    statements::Statement,
+    expr::{Ast, Enum, Expr, ExprId, ExprList, Function, NumericPolicy, Slot, Tagged, Value},
+    gc::{self, Heap, Stats},
+    natives::{Capabilities, Context, NATIVES},
+    statements::{FunctionDecl, Pattern, Statement},
     token::{Token, TokenType},
+    types::{Annotation, Type},
 };
 
-use std::{cell::RefCell, rc::Rc, time::SystemTime};
+use std::{
+    cell::RefCell, cmp::Ordering, collections::HashSet, iter, rc::Rc, slice, time::Duration,
+};
+
+/// How many calls can be nested by default.
+pub const DEFAULT_MAX_DEPTH: usize = 10_000;
+
+/// About how much of the host stack a simple recursive call takes, measured
+/// at 36 KB in debug builds and 2 KB in release builds. Calls with nested
+/// blocks and expressions take more, running out of stack before `max_depth`
+/// is also a "Stack overflow" error.
+pub const STACK_PER_CALL: usize = if cfg!(debug_assertions) {
+    40 * 1024
+} else {
+    8 * 1024
+};
+
+/// What is left of the stack for code that runs between two checks of how
+/// much is used, like natives and printing values.
+const STACK_RESERVE: usize = 1024 * 1024;
+
+/// How much stack a thread spawned by Rust gets by default, what an
+/// [`Interpreter`] assumes it has unless told otherwise.
+pub const DEFAULT_STACK_SIZE: usize = 2 * 1024 * 1024;
+
+/// How much stack the thread running a script with `max_depth` nested calls
+/// needs, see [`Interpreter::with_stack_size`].
+pub fn stack_size(max_depth: usize) -> usize {
+    max_depth
+        .saturating_mul(STACK_PER_CALL)
+        .max(8 * STACK_RESERVE)
+}
+
+/// Where the stack of the current thread is, it grows towards lower addresses.
+fn stack_address() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+/// A call in tail position, `return f(args);`, made by the caller of the
+/// function that returns it.
+#[derive(Debug)]
+pub struct TailCall {
+    declaration: Rc<FunctionDecl>,
+    closure: Rc<RefCell<Environment>>,
+    /// Where the expressions of the declaration are
+    ast: Rc<Ast>,
+    arguments: Vec<Value>,
+    paren: (usize, usize),
+    args: Vec<(usize, usize)>,
+    place: (usize, usize),
+}
 
 pub struct Interpreter {
     /// A pointer to the outermost global environment
     environment: Rc<RefCell<Environment>>,
-    _globals: Rc<RefCell<Environment>>,
+    globals: Rc<RefCell<Environment>>,
+    /// Check type annotations when calling functions
+    type_checks: bool,
+    numeric: NumericPolicy,
+    /// How many calls can be nested
+    max_depth: usize,
+    /// Where the stack was when the script started
+    stack_base: usize,
+    /// How much stack the thread running the script has
+    stack_size: usize,
+    /// The functions being called, innermost last
+    calls: Vec<Rc<FunctionDecl>>,
+    heap: Heap,
+    budget: Budget,
+    /// What the natives are allowed to do
+    capabilities: Capabilities,
 }
 
 impl Interpreter {
     pub fn new(env: Environment) -> Self {
-        let environment = Rc::new(RefCell::new(env));
-        let _globals = environment.clone();
-
-        _globals.borrow_mut().define(
-            &Token {
-                _type: TokenType::Fn,
-                lexeme: "clock".into(),
-                place: (0, 0),
-            },
-            Value::Callable(Function::Native {
-                arity: 0,
-                body: Box::new(|_| {
-                    Value::Number(
-                        SystemTime::now()
-                            .duration_since(SystemTime::UNIX_EPOCH)
-                            .unwrap()
-                            .as_millis() as f64,
-                    )
-                }),
-            }),
-        );
+        let mut heap = Heap::default();
+        let environment = heap.environment(env);
+        let globals = environment.clone();
+
+        for (name, arity, body) in NATIVES {
+            globals.borrow_mut().define(
+                &Token {
+                    _type: TokenType::Fn,
+                    lexeme: name.into(),
+                    place: (0, 0),
+                },
+                Value::Callable(Rc::new(Function::Native {
+                    name,
+                    arity,
+                    body: Box::new(body),
+                })),
+            );
+        }
 
         Self {
-            _globals,
+            globals,
             environment,
+            type_checks: false,
+            numeric: NumericPolicy::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            stack_base: stack_address(),
+            stack_size: DEFAULT_STACK_SIZE,
+            calls: Vec::new(),
+            heap,
+            budget: Budget::default(),
+            capabilities: Capabilities::default(),
         }
     }
 
-    pub fn default() -> Self {
-        Self::new(Environment::default())
+    pub fn with_type_checks(mut self, enabled: bool) -> Self {
+        self.type_checks = enabled;
+        self
+    }
+
+    pub fn with_numeric_policy(mut self, policy: NumericPolicy) -> Self {
+        self.numeric = policy;
+        self
+    }
+
+    /// Calls nested deeper than `depth` are a "Stack overflow" error. Deep
+    /// recursion also needs a thread with [`stack_size`] of stack, see
+    /// [`with_stack_size`](Self::with_stack_size).
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// How much stack the thread running the interpreter has,
+    /// [`DEFAULT_STACK_SIZE`] by default. Code nested too deeply for it is a
+    /// "Stack overflow" error before it runs out.
+    pub fn with_stack_size(mut self, bytes: usize) -> Self {
+        self.stack_size = bytes;
+        self
+    }
+
+    /// Values, environments and calls take bytes from a budget, running out
+    /// of it is a [`CompileError::OutOfMemory`].
+    pub fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.heap.set_limit(bytes);
+        self
+    }
+
+    /// Every executed statement and evaluated expression is a step, taking
+    /// more than `steps` in a call to [`interpret`](Self::interpret)
+    /// interrupts it.
+    pub fn with_max_steps(mut self, steps: u64) -> Self {
+        self.budget.set_steps(steps);
+        self
+    }
+
+    /// Calls to [`interpret`](Self::interpret) running for longer than
+    /// `timeout` are interrupted.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.budget.set_timeout(timeout);
+        self
+    }
+
+    /// Replaces what the natives are allowed to do, reading the clock by
+    /// default. Natives without the capability fail with "Permission denied".
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// What the cycle collector has done so far.
+    pub fn gc_stats(&self) -> Stats {
+        self.heap.stats()
+    }
+
+    /// Fails before the stack runs out, however the code running at `place`
+    /// is nested.
+    fn check_stack(&self, place: (usize, usize)) -> Result<(), CompileError> {
+        let used = self.stack_base.saturating_sub(stack_address());
+        let reserve = (self.stack_size / 2).min(STACK_RESERVE);
+
+        if used > self.stack_size - reserve {
+            let names = self.calls.iter().rev().map(|d| d.name.lexeme.as_str());
+            return Err(stack_overflow(place, "nested too deeply", names));
+        }
+
+        Ok(())
+    }
+
+    /// Takes what the new `value` made at `place` holds from the budget.
+    fn allocate(&mut self, place: (usize, usize), value: &Value) -> Result<(), CompileError> {
+        self.heap
+            .allocate(gc::size(value), iter::empty())
+            .map_err(|e| CompileError::OutOfMemory(place, e))
     }
 
-    fn evaluate(&mut self, expr: &Expr) -> Result<Value, CompileError> {
+    /// Tracks a new environment for the code at `place`, taking it from the
+    /// budget.
+    fn environment(
+        &mut self,
+        place: (usize, usize),
+        env: Environment,
+    ) -> Result<Rc<RefCell<Environment>>, CompileError> {
+        self.heap
+            .allocate(gc::FRAME, iter::empty())
+            .map_err(|e| CompileError::OutOfMemory(place, e))?;
+
+        Ok(self.heap.environment(env))
+    }
+
+    fn evaluate(&mut self, ast: &Rc<Ast>, expr: ExprId) -> Result<Value, CompileError> {
+        self.budget
+            .step()
+            .map_err(|interrupt| CompileError::Interrupted(ast.place(expr), interrupt))?;
+        self.check_stack(ast.place(expr))?;
+
         // TODO Report error place, the place is in the token
 
-        let value = match expr {
-            Expr::Literal(value) => value.clone(),
-            Expr::Grouping(group) => self.evaluate(group)?,
+        let value = match ast[expr] {
+            Expr::Literal(value) => ast[value].clone(),
+            Expr::Grouping(group) => self.evaluate(ast, group)?,
             Expr::Unary(op, rhs) => {
-                let rhs = self.evaluate(rhs)?;
+                let rhs = self.evaluate(ast, rhs)?;
+                let op = &ast[op];
 
                 // switch operator from token to tokenType
                 match op._type {
-                    TokenType::Minus => rhs.neg()?,
+                    TokenType::Minus => at(op, rhs.neg(self.numeric))?,
                     TokenType::Bang => Value::Boolean(!rhs.is_truthy()),
                     // TODO Report error
                     _ => unreachable!(),
                 }
             }
             Expr::Binary(lhs, op, rhs) => {
-                let lhs = self.evaluate(lhs)?;
-                let rhs = self.evaluate(rhs)?;
+                let lhs = self.evaluate(ast, lhs)?;
+                let rhs = self.evaluate(ast, rhs)?;
+                let op = &ast[op];
 
                 // switch operator from token to tokenType
                 match op._type {
-                    TokenType::Minus => lhs.sub(rhs)?,
-                    TokenType::Plus => lhs.add(rhs)?,
-                    TokenType::Slash => lhs.div(rhs)?,
-                    TokenType::Star => lhs.mul(rhs)?,
+                    TokenType::Minus => at(op, lhs.sub(rhs, self.numeric))?,
+                    TokenType::Plus => {
+                        let value = at(op, lhs.add(rhs, self.numeric))?;
+                        self.allocate(op.place, &value)?;
+                        value
+                    }
+                    TokenType::Slash => at(op, lhs.div(rhs, self.numeric))?,
+                    TokenType::Star => at(op, lhs.mul(rhs, self.numeric))?,
+                    TokenType::Percent => at(op, lhs.rem(rhs, self.numeric))?,
 
-                    TokenType::Greater => Value::Boolean(lhs > rhs),
-                    TokenType::GreaterEqual => Value::Boolean(lhs >= rhs),
-                    TokenType::Less => Value::Boolean(lhs < rhs),
-                    TokenType::LessEqual => Value::Boolean(lhs <= rhs),
+                    TokenType::Greater => compare(&lhs, &rhs, op, Ordering::is_gt)?,
+                    TokenType::GreaterEqual => compare(&lhs, &rhs, op, Ordering::is_ge)?,
+                    TokenType::Less => compare(&lhs, &rhs, op, Ordering::is_lt)?,
+                    TokenType::LessEqual => compare(&lhs, &rhs, op, Ordering::is_le)?,
 
                     TokenType::EqualEqual => Value::Boolean(lhs == rhs),
                     TokenType::BangEqual => Value::Boolean(lhs != rhs),
@@ -93,9 +272,9 @@ impl Interpreter {
                 }
             }
             Expr::Logical(lhs, op, rhs) => {
-                let left = self.evaluate(lhs)?;
+                let left = self.evaluate(ast, lhs)?;
 
-                if op._type == TokenType::Or {
+                if ast[op]._type == TokenType::Or {
                     if left.is_truthy() {
                         return Ok(left);
                     }
@@ -103,57 +282,76 @@ impl Interpreter {
                     return Ok(left);
                 }
 
-                self.evaluate(rhs)?
+                self.evaluate(ast, rhs)?
             }
-            Expr::Variable(name) => self.environment.borrow_mut().get(name)?,
-            Expr::Assign(name, expr) => {
-                let value = self.evaluate(expr)?;
-                self.environment.borrow_mut().assign(name, value.clone())?;
+            Expr::Variable(name, slot) => self.get(&ast[name], slot)?,
+            Expr::Assign(name, slot, value) => {
+                let value = self.evaluate(ast, value)?;
+
+                match slot {
+                    Some(slot) => Environment::assign_at(&self.environment, slot, value.clone()),
+                    None => self
+                        .globals
+                        .borrow_mut()
+                        .assign(&ast[name], value.clone())?,
+                }
 
                 value
             }
-            Expr::Call(calle, paren, unevaluated_args) => {
-                let calle = self.evaluate(calle)?;
-
-                let mut arguments = Vec::with_capacity(unevaluated_args.len());
-                for arg in unevaluated_args {
-                    arguments.push(self.evaluate(arg)?);
-                }
-
-                if let Value::Callable(f) = calle {
-                    // TODO are function calls sound? Maybe.
-                    match f {
-                        Function::Native { arity, body } => {
-                            check_arity(paren, arity, arguments.len())?;
-                            body(&arguments)
+            Expr::Call(callee, paren, args) => {
+                let callee = self.evaluate(ast, callee)?;
+                let arguments = self.arguments(ast, args)?;
+
+                self.call(
+                    callee,
+                    arguments,
+                    ast[paren].place,
+                    self.places(ast, args),
+                    ast.place(expr),
+                )?
+            }
+            Expr::Tuple(elements) => {
+                let value = Value::Tuple(self.arguments(ast, elements)?.into());
+                self.allocate(ast.place(expr), &value)?;
+                value
+            }
+            // The resolver rejects them, there are no classes
+            Expr::This(keyword) | Expr::Super(keyword, _) => {
+                let keyword = &ast[keyword];
+
+                return Err(CompileError::Interpreter(
+                    keyword.place,
+                    format!("Can't use '{}' outside of a class.", keyword.lexeme),
+                ));
+            }
+            Expr::Get(object, name) => {
+                let name = &ast[name];
+
+                match self.evaluate(ast, object)? {
+                    Value::Enum(ty) => match ty.variant(&name.lexeme) {
+                        Some(variant) if ty.variants[variant].1 == 0 => {
+                            Value::Tagged(Rc::new(Tagged {
+                                ty,
+                                variant,
+                                payload: Vec::new(),
+                            }))
                         }
-                        Function::User {
-                            params,
-                            body,
-                            closure,
-                            ..
-                        } => {
-                            check_arity(paren, params.len(), arguments.len())?;
-                            let env = Rc::new(RefCell::new(Environment::from(&closure)));
-
-                            for (param, argument) in params.iter().zip(arguments.drain(..)) {
-                                env.borrow_mut().define(param, argument);
-                            }
-
-                            match self.execute_block(&body, env) {
-                                Ok(_) => Value::Nil,
-                                Err(CompileError::Return(value)) => value,
-                                Err(other) => {
-                                    return Err(other);
-                                }
-                            }
+                        Some(variant) => {
+                            Value::Callable(Rc::new(Function::Constructor { ty, variant }))
                         }
+                        None => {
+                            return Err(CompileError::Interpreter(
+                                name.place,
+                                format!("Undefined variant {}.{}.", ty.name, name.lexeme),
+                            ))
+                        }
+                    },
+                    _ => {
+                        return Err(CompileError::Interpreter(
+                            name.place,
+                            "Only enums have properties.".into(),
+                        ))
                     }
-                } else {
-                    return Err(CompileError::Interpreter(
-                        paren.place,
-                        "Not a callable object.".into(),
-                    ));
                 }
             }
         };
@@ -161,48 +359,165 @@ impl Interpreter {
         Ok(value)
     }
 
-    pub fn interpret(&mut self, statements: &[Statement]) -> Result<(), CompileError> {
+    /// Runs a script whose expressions are in `ast`, [`CompileError::Interrupted`]
+    /// if it runs out of its budget. The functions it declares keep the tree,
+    /// later scripts can call them whatever tree they come from.
+    pub fn interpret(
+        &mut self,
+        statements: &[Statement],
+        ast: &Rc<Ast>,
+    ) -> Result<(), CompileError> {
+        self.budget.start();
+        self.stack_base = stack_address();
+        self.execute(ast, statements)
+    }
+
+    fn execute(&mut self, ast: &Rc<Ast>, statements: &[Statement]) -> Result<(), CompileError> {
         for stmt in statements {
+            // Blocks take a step with their first statement
+            if !matches!(stmt, Statement::Block(_)) {
+                if let Some(place) = stmt.place(ast) {
+                    self.budget
+                        .step()
+                        .map_err(|interrupt| CompileError::Interrupted(place, interrupt))?;
+                }
+            }
+
             match stmt {
-                Statement::Print(value) => self.print_statement(value)?,
-                Statement::Expresion(expr) => self.expresion_statement(expr)?,
-                Statement::Var(token, expr) => {
-                    let value = self.evaluate(expr)?;
-                    self.environment.borrow_mut().define(token, value)
-                }
-                Statement::Block(statements) => self.execute_block(
-                    statements,
-                    Rc::new(RefCell::new(Environment::from(&self.environment))),
-                )?,
+                Statement::Print(value) => self.print_statement(ast, *value)?,
+                Statement::Expresion(expr) => self.expresion_statement(ast, *expr)?,
+                Statement::Var(var) => {
+                    let value = match var.initializer {
+                        Some(expr) => self.evaluate(ast, expr)?,
+                        None => Value::Nil,
+                    };
+                    self.environment.borrow_mut().define(&var.name, value)
+                }
+                Statement::Unpack(names, expr) => {
+                    let place = (names[0].place.0, names[names.len() - 1].place.1);
+
+                    let values = match self.evaluate(ast, *expr)? {
+                        Value::Tuple(values) => values,
+                        other => {
+                            return Err(CompileError::Interpreter(
+                                place,
+                                format!("Can only unpack tuples, got {}.", other),
+                            ))
+                        }
+                    };
+
+                    if values.len() != names.len() {
+                        return Err(CompileError::Interpreter(
+                            place,
+                            format!(
+                                "Expected {} values to unpack but got {}.",
+                                names.len(),
+                                values.len()
+                            ),
+                        ));
+                    }
+
+                    for (name, value) in names.iter().zip(values.iter()) {
+                        self.environment.borrow_mut().define(name, value.clone());
+                    }
+                }
+                Statement::Block(statements) => {
+                    let place = stmt.place(ast).unwrap_or_default();
+                    let env = self.environment(place, Environment::from(&self.environment))?;
+                    self.execute_block(ast, statements, env)?;
+                }
                 Statement::If(condition, then_branch, maybe_else_branch) => {
-                    if self.evaluate(condition)?.is_truthy() {
-                        self.interpret(&[*then_branch.clone()])?;
+                    if self.evaluate(ast, *condition)?.is_truthy() {
+                        self.execute(ast, slice::from_ref(then_branch))?;
                     } else if let Some(else_branch) = maybe_else_branch {
-                        self.interpret(&[*else_branch.clone()])?;
+                        self.execute(ast, slice::from_ref(else_branch))?;
                     }
                 }
                 Statement::While(condition, body) => {
-                    while self.evaluate(condition)?.is_truthy() {
-                        self.interpret(&[*body.clone()])?;
+                    while self.evaluate(ast, *condition)?.is_truthy() {
+                        self.execute(ast, slice::from_ref(body))?;
                     }
                 }
-                Statement::Function(name, params, body) => {
-                    let function = Value::Callable(Function::User {
-                        name: name.clone(),
-                        params: params.clone(),
-                        body: body.clone(),
+                Statement::Function(function) => {
+                    let value = Value::Callable(self.heap.function(Function::User {
+                        declaration: function.clone(),
                         closure: self.environment.clone(),
-                    });
+                        ast: ast.clone(),
+                    }));
+
+                    self.environment.borrow_mut().define(&function.name, value);
+                }
+                Statement::Enum(decl) => {
+                    let ty = Enum {
+                        name: decl.name.lexeme.to_string(),
+                        variants: decl
+                            .variants
+                            .iter()
+                            .map(|(name, fields)| (name.lexeme.to_string(), fields.len()))
+                            .collect(),
+                    };
+
+                    self.environment
+                        .borrow_mut()
+                        .define(&decl.name, Value::Enum(Rc::new(ty)));
+                }
+                Statement::Match(keyword, scrutinee, arms) => {
+                    let value = self.evaluate(ast, *scrutinee)?;
 
-                    self.environment.borrow_mut().define(name, function);
+                    for (pattern, arm) in arms {
+                        if let Some(bindings) = self.match_pattern(pattern, &value)? {
+                            let env = self
+                                .environment(keyword.place, Environment::from(&self.environment))?;
+
+                            for (name, value) in bindings {
+                                env.borrow_mut().define(name, value);
+                            }
+
+                            self.execute_block(ast, slice::from_ref(arm), env)?;
+                            break;
+                        }
+                    }
                 }
                 Statement::Return(_, value) => {
-                    let value = if Expr::Literal(Value::Nil) == *value {
-                        Value::Nil
-                    } else {
-                        self.evaluate(value)?
+                    let (callee, paren, args) = match ast[*value] {
+                        // Made by the caller so tail recursion does not grow the stack
+                        Expr::Call(callee, paren, args) => (callee, paren, args),
+                        Expr::Literal(literal) if ast[literal] == Value::Nil => {
+                            return Err(CompileError::Return(Value::Nil))
+                        }
+                        _ => return Err(CompileError::Return(self.evaluate(ast, *value)?)),
                     };
 
+                    let callee = self.evaluate(ast, callee)?;
+                    let arguments = self.arguments(ast, args)?;
+
+                    if let Value::Callable(function) = &callee {
+                        if let Function::User {
+                            declaration,
+                            closure,
+                            ast: body,
+                        } = &**function
+                        {
+                            return Err(CompileError::TailCall(Box::new(TailCall {
+                                declaration: declaration.clone(),
+                                closure: closure.clone(),
+                                ast: body.clone(),
+                                arguments,
+                                paren: ast[paren].place,
+                                args: self.places(ast, args),
+                                place: ast.place(*value),
+                            })));
+                        }
+                    }
+
+                    let value = self.call(
+                        callee,
+                        arguments,
+                        ast[paren].place,
+                        self.places(ast, args),
+                        ast.place(*value),
+                    )?;
+
                     return Err(CompileError::Return(value));
                 }
             }
@@ -211,14 +526,212 @@ impl Interpreter {
         Ok(())
     }
 
-    fn expresion_statement(&mut self, s: &Expr) -> Result<(), CompileError> {
-        self.evaluate(s)?;
+    fn arguments(&mut self, ast: &Rc<Ast>, args: ExprList) -> Result<Vec<Value>, CompileError> {
+        let mut arguments = Vec::with_capacity(args.len());
+        for arg in &ast[args] {
+            arguments.push(self.evaluate(ast, *arg)?);
+        }
+
+        Ok(arguments)
+    }
+
+    /// Where the arguments come from, only needed to report type errors.
+    fn places(&self, ast: &Rc<Ast>, args: ExprList) -> Vec<(usize, usize)> {
+        if self.type_checks {
+            ast[args].iter().map(|arg| ast.place(*arg)).collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Calls `callee` with the `arguments` that come from the code at `args`.
+    /// `paren` and `place` are the closing parenthesis and the whole call.
+    fn call(
+        &mut self,
+        callee: Value,
+        arguments: Vec<Value>,
+        paren: (usize, usize),
+        args: Vec<(usize, usize)>,
+        place: (usize, usize),
+    ) -> Result<Value, CompileError> {
+        let function = match callee {
+            Value::Callable(function) => function,
+            _ => {
+                return Err(CompileError::Interpreter(
+                    paren,
+                    "Not a callable object.".into(),
+                ))
+            }
+        };
+
+        let (declaration, closure, ast) = match &*function {
+            Function::Native { arity, body, .. } => {
+                check_arity(paren, *arity, arguments.len())?;
+                let mut cx = Context {
+                    heap: &mut self.heap,
+                    capabilities: &self.capabilities,
+                };
+
+                let value = body(&mut cx, &arguments)
+                    .map_err(|msg| CompileError::Interpreter(paren, msg))?;
+                self.allocate(paren, &value)?;
+                return Ok(value);
+            }
+            Function::Constructor { ty, variant } => {
+                check_arity(paren, ty.variants[*variant].1, arguments.len())?;
+
+                let value = Value::Tagged(Rc::new(Tagged {
+                    ty: ty.clone(),
+                    variant: *variant,
+                    payload: arguments,
+                }));
+                self.allocate(paren, &value)?;
+                return Ok(value);
+            }
+            Function::User {
+                declaration,
+                closure,
+                ast,
+            } => (declaration.clone(), closure.clone(), ast.clone()),
+            Function::Closure(_) => unreachable!("only the virtual machine creates them"),
+        };
+
+        let call = TailCall {
+            declaration,
+            closure,
+            ast,
+            arguments,
+            paren,
+            args,
+            place,
+        };
+
+        self.calls.push(call.declaration.clone());
+        let value = self.call_user(call);
+        self.calls.pop();
+
+        value
+    }
+
+    fn call_user(&mut self, mut call: TailCall) -> Result<Value, CompileError> {
+        // The functions whose return annotation applies to the value, with
+        // the call it comes from. Tail calls run in this loop instead of
+        // recursing, and only add their annotation the first time.
+        let mut returns: Vec<(Rc<FunctionDecl>, (usize, usize))> = Vec::new();
+        let mut annotated: HashSet<*const FunctionDecl> = HashSet::new();
+
+        let value = loop {
+            let declaration = call.declaration.clone();
+            let ast = call.ast.clone();
+            let FunctionDecl {
+                params,
+                returns: annotation,
+                body,
+                ..
+            } = &*declaration;
+
+            if let Some(top) = self.calls.last_mut() {
+                *top = declaration.clone();
+            }
+
+            check_arity(call.paren, params.len(), call.arguments.len())?;
+
+            if self.type_checks {
+                for ((param, value), place) in params.iter().zip(&call.arguments).zip(&call.args) {
+                    check_type(&param.annotation, value, *place)?;
+                }
+
+                // The same annotation checks the same value, however many
+                // times the function was tail called
+                if annotation.is_some() && annotated.insert(Rc::as_ptr(&declaration)) {
+                    returns.push((declaration.clone(), call.place));
+                }
+            }
+
+            if self.calls.len() > self.max_depth {
+                let names = self.calls.iter().rev().map(|d| d.name.lexeme.as_str());
+                return Err(overflow(call.paren, self.max_depth, names));
+            }
+            self.check_stack(call.paren)?;
+
+            let env = self.environment(call.paren, Environment::from(&call.closure))?;
+
+            for (param, argument) in params.iter().zip(call.arguments.drain(..)) {
+                env.borrow_mut().define(&param.name, argument);
+            }
+
+            match self.execute_block(&ast, body, env) {
+                Ok(_) => break Value::Nil,
+                Err(CompileError::Return(value)) => break value,
+                Err(CompileError::TailCall(next)) => call = *next,
+                Err(other) => return Err(other),
+            }
+        };
+
+        // Innermost first, like nested calls would
+        for (declaration, place) in returns.iter().rev() {
+            check_type(&declaration.returns, &value, *place)?;
+        }
+
+        Ok(value)
+    }
+
+    /// Returns the values bound by the pattern if it matches.
+    fn match_pattern<'p>(
+        &mut self,
+        pattern: &'p Pattern,
+        value: &Value,
+    ) -> Result<Option<Vec<(&'p Token, Value)>>, CompileError> {
+        let (ty_name, slot, variant_name, bindings) = match pattern {
+            Pattern::Wildcard(_) => return Ok(Some(Vec::new())),
+            Pattern::Variant(ty, slot, variant, bindings) => (ty, *slot, variant, bindings),
+        };
+
+        let ty = match self.get(ty_name, slot)? {
+            Value::Enum(ty) => ty,
+            _ => {
+                return Err(CompileError::Interpreter(
+                    ty_name.place,
+                    format!("{} is not an enum.", ty_name.lexeme),
+                ))
+            }
+        };
+
+        let variant = match ty.variant(&variant_name.lexeme) {
+            Some(variant) => variant,
+            None => {
+                return Err(CompileError::Interpreter(
+                    variant_name.place,
+                    format!("Undefined variant {}.{}.", ty.name, variant_name.lexeme),
+                ))
+            }
+        };
+        check_arity(variant_name.place, ty.variants[variant].1, bindings.len())?;
+
+        match value {
+            Value::Tagged(t) if Rc::ptr_eq(&t.ty, &ty) && t.variant == variant => Ok(Some(
+                bindings.iter().zip(t.payload.iter().cloned()).collect(),
+            )),
+            _ => Ok(None),
+        }
+    }
+
+    /// The value of a variable the resolver found at `slot`.
+    fn get(&self, name: &Token, slot: Option<Slot>) -> Result<Value, CompileError> {
+        match slot {
+            Some(slot) => Ok(Environment::get_at(&self.environment, slot)),
+            None => self.globals.borrow().get(name),
+        }
+    }
+
+    fn expresion_statement(&mut self, ast: &Rc<Ast>, s: ExprId) -> Result<(), CompileError> {
+        self.evaluate(ast, s)?;
 
         Ok(())
     }
 
-    fn print_statement(&mut self, s: &Expr) -> Result<(), CompileError> {
-        let v = self.evaluate(s)?;
+    fn print_statement(&mut self, ast: &Rc<Ast>, s: ExprId) -> Result<(), CompileError> {
+        let v = self.evaluate(ast, s)?;
         println!("{}", v);
         // TODO flush buffer?
 
@@ -227,22 +740,113 @@ impl Interpreter {
 
     fn execute_block(
         &mut self,
+        ast: &Rc<Ast>,
         statements: &[Statement],
         environment: Rc<RefCell<Environment>>,
     ) -> Result<(), CompileError> {
         let previous = self.environment.clone();
         self.environment = environment;
-        let result = self.interpret(statements);
+        let result = self.execute(ast, statements);
         self.environment = previous;
 
         result
     }
 }
 
-fn check_arity(fn_name: &Token, params: usize, arguments: usize) -> Result<(), CompileError> {
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new(Environment::default())
+    }
+}
+
+/// Fails if `value`, which comes from the code at `place`, does not match the annotation.
+pub(crate) fn check_type(
+    annotation: &Option<Annotation>,
+    value: &Value,
+    place: (usize, usize),
+) -> Result<(), CompileError> {
+    match annotation {
+        Some(annotation) if !annotation.ty.accepts(&Type::of(value)) => {
+            Err(CompileError::TypeMismatch(
+                (annotation.place, annotation.ty.clone()),
+                (place, Type::of(value)),
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The error for a call nested deeper than `max_depth`, with the functions
+/// being called (innermost first) summarized: `f (3 times), g, main`.
+pub(crate) fn overflow<'n>(
+    paren: (usize, usize),
+    max_depth: usize,
+    names: impl Iterator<Item = &'n str>,
+) -> CompileError {
+    let reason = format!("more than {} nested calls", max_depth);
+    stack_overflow(paren, &reason, names)
+}
+
+/// A "Stack overflow" error at `place` for the given reason, with the
+/// functions being called summarized.
+fn stack_overflow<'n>(
+    place: (usize, usize),
+    reason: &str,
+    names: impl Iterator<Item = &'n str>,
+) -> CompileError {
+    let mut runs: Vec<(&str, usize)> = Vec::new();
+
+    for name in names {
+        match runs.last_mut() {
+            Some((last, times)) if *last == name => *times += 1,
+            _ => runs.push((name, 1)),
+        }
+    }
+
+    let mut summary: Vec<String> = runs
+        .iter()
+        .take(3)
+        .map(|(name, times)| match times {
+            1 => name.to_string(),
+            _ => format!("{} ({} times)", name, times),
+        })
+        .collect();
+
+    if runs.len() > 3 {
+        summary.push("...".into());
+    }
+
+    if summary.is_empty() {
+        summary.push("top-level code".into());
+    }
+
+    CompileError::Interpreter(
+        place,
+        format!("Stack overflow, {}: {}.", reason, summary.join(", ")),
+    )
+}
+
+/// Unordered values, `NaN`, make every comparison `false`.
+fn compare(
+    lhs: &Value,
+    rhs: &Value,
+    op: &Token,
+    test: fn(Ordering) -> bool,
+) -> Result<Value, CompileError> {
+    let ordering = at(op, lhs.compare(rhs))?;
+
+    Ok(Value::Boolean(ordering.is_some_and(test)))
+}
+
+/// Reports the error of an operation at its operator.
+fn at<T>(op: &Token, result: Result<T, String>) -> Result<T, CompileError> {
+    result.map_err(|msg| CompileError::Interpreter(op.place, msg))
+}
+
+fn check_arity(place: (usize, usize), params: usize, arguments: usize) -> Result<(), CompileError> {
     if params != arguments {
         Err(CompileError::Interpreter(
-            fn_name.place,
+            place,
             format!("Expected {} arguments but got {}.", params, arguments),
         ))
     } else {
@@ -0,0 +1,22 @@
+pub mod budget;
+pub mod checker;
+pub mod chunk;
+pub mod compiler;
+pub mod environment;
+pub mod errors;
+pub mod exhaustiveness;
+pub mod expr;
+pub mod gc;
+pub mod interpreter;
+pub mod lints;
+pub mod luxc;
+pub mod natives;
+pub mod optimizer;
+pub mod parser;
+pub mod resolver;
+pub mod scanner;
+pub mod statements;
+pub mod symbol;
+pub mod token;
+pub mod types;
+pub mod vm;
@@ -0,0 +1,363 @@
+use crate::{
+    errors::CompileError,
+    expr::{Ast, Expr, ExprId, Value},
+    natives::NATIVES,
+    statements::{Pattern, Statement},
+    symbol::Symbol,
+    token::Token,
+};
+
+use std::collections::{HashMap, HashSet};
+
+/// The kinds of warnings, by the name used in flags and pragmas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    Unused,
+    Unreachable,
+    Shadowing,
+    ConstantCondition,
+    Undeclared,
+    NonExhaustive,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 6] = [
+        Lint::Unused,
+        Lint::Unreachable,
+        Lint::Shadowing,
+        Lint::ConstantCondition,
+        Lint::Undeclared,
+        Lint::NonExhaustive,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::Unused => "unused",
+            Lint::Unreachable => "unreachable",
+            Lint::Shadowing => "shadowing",
+            Lint::ConstantCondition => "constant-condition",
+            Lint::Undeclared => "undeclared",
+            Lint::NonExhaustive => "non-exhaustive",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+}
+
+/// Which warnings are reported and whether they stop the script from running.
+#[derive(Debug, Clone)]
+pub struct Warnings {
+    pub enabled: HashSet<Lint>,
+    /// `-Werror`
+    pub fatal: bool,
+}
+
+impl Default for Warnings {
+    /// The warnings that are almost always a bug.
+    fn default() -> Self {
+        Self {
+            enabled: [Lint::Unreachable, Lint::NonExhaustive].into(),
+            fatal: false,
+        }
+    }
+}
+
+impl Warnings {
+    /// Drops the warnings that are not enabled or that are silenced by a
+    /// `// lux-allow(kind, ...)` pragma. A pragma applies to its own line, or
+    /// to the next one if it is the only thing in its line.
+    pub fn filter(&self, source: &str, warnings: Vec<CompileError>) -> Vec<CompileError> {
+        let allowed = pragmas(source);
+
+        warnings
+            .into_iter()
+            .filter(|warning| match warning {
+                CompileError::Warning(place, lint, _) => {
+                    let line = source[..place.0.min(source.len())].matches('\n').count();
+
+                    self.enabled.contains(lint)
+                        && !allowed.get(&line).is_some_and(|lints| lints.contains(lint))
+                }
+                _ => true,
+            })
+            .collect()
+    }
+}
+
+fn pragmas(source: &str) -> HashMap<usize, HashSet<Lint>> {
+    let mut allowed: HashMap<usize, HashSet<Lint>> = HashMap::new();
+
+    for (line, text) in source.lines().enumerate() {
+        let pragma = match text.find("// lux-allow(") {
+            Some(start) => &text[start..],
+            None => continue,
+        };
+
+        let lints = pragma["// lux-allow(".len()..]
+            .split(')')
+            .next()
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|name| Lint::from_name(name.trim()));
+
+        let target = if text.trim_start().starts_with("//") {
+            line + 1
+        } else {
+            line
+        };
+
+        allowed.entry(target).or_default().extend(lints);
+    }
+
+    allowed
+}
+
+struct Local {
+    name: Token,
+    kind: &'static str,
+    used: bool,
+}
+
+/// Reports code that is valid but probably wrong. It runs after the resolver,
+/// so scoping errors are already ruled out.
+pub struct Linter<'a> {
+    ast: &'a Ast,
+    scopes: Vec<Vec<Local>>,
+    /// Every name declared at the top level, anywhere in the script
+    globals: HashSet<Symbol>,
+    warnings: Vec<CompileError>,
+}
+
+impl<'a> Linter<'a> {
+    pub fn check(statements: &[Statement], ast: &'a Ast) -> Vec<CompileError> {
+        let mut globals: HashSet<Symbol> = NATIVES.iter().map(|n| Symbol::intern(n.0)).collect();
+
+        for stmt in statements {
+            match stmt {
+                Statement::Var(var) => globals.insert(var.name.lexeme.clone()),
+                Statement::Function(function) => globals.insert(function.name.lexeme.clone()),
+                Statement::Enum(decl) => globals.insert(decl.name.lexeme.clone()),
+                Statement::Unpack(names, _) => {
+                    globals.extend(names.iter().map(|n| n.lexeme.clone()));
+                    true
+                }
+                _ => false,
+            };
+        }
+
+        let mut linter = Self {
+            ast,
+            scopes: Vec::new(),
+            globals,
+            warnings: Vec::new(),
+        };
+
+        linter.block(statements);
+        linter.warnings
+    }
+
+    fn warn(&mut self, place: (usize, usize), lint: Lint, msg: String) {
+        self.warnings.push(CompileError::Warning(place, lint, msg));
+    }
+
+    fn block(&mut self, statements: &[Statement]) {
+        let mut returned = false;
+
+        for stmt in statements {
+            if returned {
+                if let Some(place) = stmt.place(self.ast) {
+                    self.warn(place, Lint::Unreachable, "Unreachable statement.".into());
+                }
+                returned = false;
+            }
+
+            self.statement(stmt);
+
+            if let Statement::Return(..) = stmt {
+                returned = true;
+            }
+        }
+    }
+
+    fn scoped(&mut self, locals: Vec<(&Token, &'static str)>, statements: &[Statement]) {
+        self.scopes.push(Vec::new());
+
+        for (name, kind) in locals {
+            self.declare(name, kind);
+        }
+
+        self.block(statements);
+
+        for local in self.scopes.pop().unwrap_or_default() {
+            if !local.used {
+                self.warn(
+                    local.name.place,
+                    Lint::Unused,
+                    format!("Unused {} {}.", local.kind, local.name.lexeme),
+                );
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &Token, kind: &'static str) {
+        if self.scopes.is_empty() {
+            return;
+        }
+
+        let shadows = self.lookup(&name.lexeme).is_some() || self.globals.contains(&name.lexeme);
+        if shadows {
+            self.warn(
+                name.place,
+                Lint::Shadowing,
+                format!("{} shadows a variable from an outer scope.", name.lexeme),
+            );
+        }
+
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(Local {
+                name: name.clone(),
+                kind,
+                used: false,
+            });
+        }
+    }
+
+    fn lookup(&mut self, name: &Symbol) -> Option<&mut Local> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.iter_mut().rev().find(|l| l.name.lexeme == *name))
+    }
+
+    fn condition(&mut self, condition: ExprId, is_loop: bool) {
+        let ast = self.ast;
+        let mut inner = ast[condition];
+        while let Expr::Grouping(expr) = inner {
+            inner = ast[expr];
+        }
+
+        if let Expr::Literal(value) = inner {
+            let value = &ast[value];
+
+            // `while (true)` is the way to loop forever
+            if !(is_loop && *value == Value::Boolean(true)) {
+                self.warn(
+                    ast.place(condition),
+                    Lint::ConstantCondition,
+                    format!("Condition is always {}.", value.is_truthy()),
+                );
+            }
+        }
+
+        self.expression(condition);
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Print(expr) | Statement::Expresion(expr) => self.expression(*expr),
+            Statement::Var(var) => {
+                if let Some(initializer) = var.initializer {
+                    self.expression(initializer);
+                }
+
+                self.declare(&var.name, "variable");
+            }
+            Statement::Unpack(names, expr) => {
+                self.expression(*expr);
+
+                for name in names {
+                    self.declare(name, "variable");
+                }
+            }
+            Statement::Block(statements) => self.scoped(Vec::new(), statements),
+            Statement::If(condition, then_branch, else_branch) => {
+                self.condition(*condition, false);
+                self.statement(then_branch);
+
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            Statement::While(condition, body) => {
+                self.condition(*condition, true);
+                self.statement(body);
+            }
+            Statement::Function(function) => {
+                self.declare(&function.name, "function");
+
+                let params = function
+                    .params
+                    .iter()
+                    .map(|p| (&p.name, "parameter"))
+                    .collect();
+
+                self.scoped(params, &function.body);
+            }
+            Statement::Return(_, expr) => self.expression(*expr),
+            Statement::Enum(decl) => self.declare(&decl.name, "enum"),
+            Statement::Match(_, scrutinee, arms) => {
+                self.expression(*scrutinee);
+
+                for (pattern, arm) in arms {
+                    let bindings = match pattern {
+                        Pattern::Variant(ty, _, _, bindings) => {
+                            self.used(ty);
+                            bindings.iter().map(|b| (b, "binding")).collect()
+                        }
+                        Pattern::Wildcard(_) => Vec::new(),
+                    };
+
+                    self.scoped(bindings, std::slice::from_ref(arm));
+                }
+            }
+        }
+    }
+
+    fn used(&mut self, name: &Token) {
+        if let Some(local) = self.lookup(&name.lexeme) {
+            local.used = true;
+        }
+    }
+
+    fn expression(&mut self, expr: ExprId) {
+        let ast = self.ast;
+
+        match ast[expr] {
+            Expr::Literal(_) | Expr::This(_) | Expr::Super(..) => {}
+            Expr::Binary(lhs, _, rhs) | Expr::Logical(lhs, _, rhs) => {
+                self.expression(lhs);
+                self.expression(rhs);
+            }
+            Expr::Unary(_, expr) | Expr::Grouping(expr) | Expr::Get(expr, _) => {
+                self.expression(expr)
+            }
+            Expr::Variable(name, _) => self.used(&ast[name]),
+            Expr::Assign(name, slot, value) => {
+                self.expression(value);
+
+                let name = &ast[name];
+                if slot.is_none() && !self.globals.contains(&name.lexeme) {
+                    self.warn(
+                        name.place,
+                        Lint::Undeclared,
+                        format!("Assignment to undeclared variable {}.", name.lexeme),
+                    );
+                }
+            }
+            Expr::Call(callee, _, args) => {
+                self.expression(callee);
+
+                for arg in &ast[args] {
+                    self.expression(*arg);
+                }
+            }
+            Expr::Tuple(elements) => {
+                for element in &ast[elements] {
+                    self.expression(*element);
+                }
+            }
+        }
+    }
+}
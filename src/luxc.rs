@@ -0,0 +1,657 @@
+use crate::{
+    chunk::{CallSite, Chunk, Op, PatternInfo, Proto},
+    expr::{Enum, Value},
+    types::{Annotation, Type},
+};
+
+use std::rc::Rc;
+
+/// The first bytes of every `.luxc` file.
+pub const MAGIC: &[u8; 4] = b"LUXC";
+
+/// Changes whenever the instructions or the layout of the file change, files
+/// with any other version are rejected.
+pub const VERSION: u32 = 2;
+
+/// A compiled script, what a `.luxc` file holds.
+#[derive(Debug)]
+pub struct Program {
+    /// The script it was compiled from, diagnostics show its lines
+    pub file: String,
+    pub source: String,
+    /// The name of every global slot
+    pub globals: Vec<String>,
+    pub script: Proto,
+}
+
+/// Layout: the magic bytes, the version, the version of lux that wrote it,
+/// the source with its name, the globals and the script. Numbers are little
+/// endian, strings and lists are prefixed with their length.
+pub fn write(program: &Program) -> Vec<u8> {
+    let mut w = Writer(MAGIC.to_vec());
+
+    w.u32(VERSION);
+    w.str(env!("CARGO_PKG_VERSION"));
+    w.str(&program.file);
+    w.str(&program.source);
+    w.list(&program.globals, |w, name| w.str(name));
+    w.proto(&program.script);
+
+    w.0
+}
+
+/// Whether the bytes look like a `.luxc` file, of any version.
+pub fn is_compiled(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+pub fn read(bytes: &[u8]) -> Result<Program, String> {
+    if !is_compiled(bytes) {
+        return Err("Not a compiled lux file.".into());
+    }
+
+    let mut r = Reader {
+        bytes,
+        at: MAGIC.len(),
+    };
+
+    let version = r.u32()?;
+    let lux = r.str()?;
+
+    if version != VERSION {
+        return Err(format!(
+            "Compiled by lux {} with bytecode version {}, this is lux {} with version {}. Compile it again.",
+            lux,
+            version,
+            env!("CARGO_PKG_VERSION"),
+            VERSION
+        ));
+    }
+
+    let program = Program {
+        file: r.str()?,
+        source: r.str()?,
+        globals: r.list(Reader::str)?,
+        script: r.proto()?,
+    };
+
+    if r.at != bytes.len() {
+        return Err("Unexpected data at the end of the file.".into());
+    }
+
+    check(&program.script, &program.source, program.globals.len(), 0)
+        .map_err(|msg| format!("corrupt .luxc: {}", msg))?;
+
+    Ok(program)
+}
+
+/// Makes sure every instruction only refers to what the file has and every
+/// span is in the source, so the machine never indexes out of its tables or
+/// its stack. `upvalues` is the number of upvalues of the enclosing function.
+fn check(proto: &Proto, source: &str, globals: usize, upvalues: usize) -> Result<(), String> {
+    let chunk = &proto.chunk;
+    let name = &proto.name;
+
+    let annotations = proto.params.iter().chain([&proto.returns]).flatten();
+    let spans = chunk
+        .spans
+        .iter()
+        .chain(
+            chunk
+                .calls
+                .iter()
+                .flat_map(|c| c.args.iter().chain([&c.place])),
+        )
+        .chain(chunk.patterns.iter().map(|p| &p.variant_place))
+        .chain(annotations.map(|a| &a.place));
+
+    for &(start, end) in spans {
+        if start > end || !source.is_char_boundary(start) || !source.is_char_boundary(end) {
+            return Err(format!(
+                "{} points at {}..{}, outside of the source.",
+                name, start, end
+            ));
+        }
+    }
+
+    if chunk.spans.len() != chunk.code.len() {
+        return Err(format!(
+            "{} has {} instructions and {} spans.",
+            name,
+            chunk.code.len(),
+            chunk.spans.len()
+        ));
+    }
+
+    if chunk.code.last() != Some(&Op::Return) {
+        return Err(format!("{} doesn't end with a return.", name));
+    }
+
+    for &(local, index) in &proto.upvalues {
+        if !local && index as usize >= upvalues {
+            return Err(format!(
+                "{} captures upvalue {} of {}.",
+                name, index, upvalues
+            ));
+        }
+    }
+
+    for (at, op) in chunk.code.iter().enumerate() {
+        let (index, table, len) = match *op {
+            Op::Constant(n) | Op::Get(n) | Op::Enum(n) => (n, "constant", chunk.constants.len()),
+            Op::GetUpvalue(n) | Op::SetUpvalue(n) => (n, "upvalue", proto.upvalues.len()),
+            Op::GetGlobal(n) | Op::SetGlobal(n) | Op::DefineGlobal(n) => (n, "global", globals),
+            Op::Jump(n) | Op::JumpIfFalse(n) | Op::JumpIfTrue(n) => {
+                (n, "instruction", chunk.code.len())
+            }
+            Op::Call(n) | Op::TailCall(n) => (n, "call", chunk.calls.len()),
+            Op::Closure(n) => (n, "function", chunk.functions.len()),
+            Op::Pattern(n) => (n, "pattern", chunk.patterns.len()),
+            _ => continue,
+        };
+
+        if index as usize >= len {
+            return Err(format!(
+                "instruction {} of {} refers to {} {} of {}.",
+                at, name, table, index, len
+            ));
+        }
+
+        let kind = match *op {
+            Op::Get(_) if !matches!(chunk.constants[index as usize], Value::String(_)) => "a name",
+            Op::Enum(_) if !matches!(chunk.constants[index as usize], Value::Enum(_)) => "an enum",
+            _ => continue,
+        };
+
+        return Err(format!(
+            "instruction {} of {} needs {}, constant {} is {}.",
+            at, name, kind, index, chunk.constants[index as usize]
+        ));
+    }
+
+    check_stack(proto)?;
+
+    for function in &chunk.functions {
+        check(function, source, globals, proto.upvalues.len())?;
+    }
+
+    Ok(())
+}
+
+/// Where the instructions that bind the payload of a `match` arm are.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Destructuring {
+    None,
+    /// `Pattern` pushed whether the value matches
+    Tested(u32),
+    /// The value matched, `JumpIfFalse` didn't jump
+    Matched(u32),
+    /// The matched value is on top, ready for `Destructure`
+    Ready(u32),
+}
+
+/// What the stack holds before an instruction, the same on every path that
+/// leads to it.
+#[derive(Debug, Clone, PartialEq)]
+struct Stack {
+    depth: usize,
+    /// The slots captured by closures, they must be closed before they are popped
+    captured: Vec<usize>,
+    destructuring: Destructuring,
+}
+
+/// Follows every path through the code of `proto` to make sure it never
+/// pops more than it pushed, reads locals that aren't there or leaves an
+/// upvalue pointing at a popped slot.
+fn check_stack(proto: &Proto) -> Result<(), String> {
+    let chunk = &proto.chunk;
+    let name = &proto.name;
+
+    let mut before: Vec<Option<Stack>> = vec![None; chunk.code.len()];
+    let mut pending = vec![(
+        0,
+        Stack {
+            // The function and its parameters
+            depth: proto.params.len() + 1,
+            captured: Vec::new(),
+            destructuring: Destructuring::None,
+        },
+    )];
+
+    while let Some((at, stack)) = pending.pop() {
+        match &before[at] {
+            Some(seen) if *seen == stack => continue,
+            Some(_) => {
+                return Err(format!(
+                    "instruction {} of {} is reached with different stacks.",
+                    at, name
+                ))
+            }
+            None => before[at] = Some(stack.clone()),
+        }
+
+        let op = chunk.code[at];
+        let error = |msg: &str| format!("instruction {} of {} {}.", at, name, msg);
+
+        // How many values it needs on the stack, pops and pushes
+        let (needs, pops, pushes) = match op {
+            Op::Constant(_)
+            | Op::Nil
+            | Op::GetUpvalue(_)
+            | Op::GetGlobal(_)
+            | Op::Closure(_)
+            | Op::Enum(_) => (0, 0, 1),
+            Op::GetLocal(slot) => (slot as usize + 1, 0, 1),
+            Op::SetLocal(slot) => (slot as usize + 1, 0, 0),
+            Op::Pop | Op::Print | Op::DefineGlobal(_) | Op::CloseUpvalue | Op::Return => (1, 1, 0),
+            Op::SetUpvalue(_) | Op::SetGlobal(_) | Op::JumpIfFalse(_) | Op::JumpIfTrue(_) => {
+                (1, 0, 0)
+            }
+            Op::Jump(_) => (0, 0, 0),
+            Op::Negate | Op::Not | Op::Get(_) => (1, 1, 1),
+            Op::Add
+            | Op::Subtract
+            | Op::Multiply
+            | Op::Divide
+            | Op::Remainder
+            | Op::Equal
+            | Op::NotEqual
+            | Op::Greater
+            | Op::GreaterEqual
+            | Op::Less
+            | Op::LessEqual => (2, 2, 1),
+            Op::Call(site) | Op::TailCall(site) => {
+                let args = chunk.calls[site as usize].args.len();
+                (args + 1, args + 1, 1)
+            }
+            Op::Tuple(len) => (len as usize, len as usize, 1),
+            Op::Unpack(len) => (1, 1, len as usize),
+            // Pops the enum and tests the value below it
+            Op::Pattern(_) => (2, 1, 1),
+            Op::Destructure => match stack.destructuring {
+                Destructuring::Ready(index) => (1, 0, chunk.patterns[index as usize].bindings),
+                _ => return Err(error("destructures a value that didn't match")),
+            },
+        };
+
+        if stack.depth < needs {
+            return Err(error("uses more values than the stack has"));
+        }
+
+        let mut next = stack.clone();
+        next.depth = stack.depth - pops;
+
+        match op {
+            Op::CloseUpvalue => next.captured.retain(|&slot| slot < next.depth),
+            _ if next.captured.iter().any(|&slot| slot >= next.depth) && op != Op::Return => {
+                return Err(error("pops a captured local without closing it"))
+            }
+            Op::Closure(index) => {
+                let function = &chunk.functions[index as usize];
+
+                for &(local, slot) in &function.upvalues {
+                    // A function can capture the slot it is about to be stored in
+                    if local && slot as usize > stack.depth {
+                        return Err(error("captures a local the stack doesn't have"));
+                    }
+                    if local && !next.captured.contains(&(slot as usize)) {
+                        next.captured.push(slot as usize);
+                    }
+                }
+
+                next.captured.sort_unstable();
+            }
+            _ => {}
+        }
+
+        next.depth += pushes;
+        next.destructuring = match (op, stack.destructuring) {
+            (Op::Pattern(index), _) => Destructuring::Tested(index),
+            (Op::JumpIfFalse(_), Destructuring::Tested(index)) => Destructuring::Matched(index),
+            (Op::Pop, Destructuring::Matched(index)) => Destructuring::Ready(index),
+            _ => Destructuring::None,
+        };
+
+        match op {
+            Op::Return => {}
+            Op::Jump(target) => pending.push((target as usize, next)),
+            Op::JumpIfFalse(target) | Op::JumpIfTrue(target) => {
+                let jumped = Stack {
+                    destructuring: Destructuring::None,
+                    ..next.clone()
+                };
+                pending.push((target as usize, jumped));
+                pending.push((at + 1, next));
+            }
+            _ => pending.push((at + 1, next)),
+        }
+    }
+
+    Ok(())
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, n: u8) {
+        self.0.push(n);
+    }
+
+    fn u32(&mut self, n: u32) {
+        self.0.extend(n.to_le_bytes());
+    }
+
+    fn usize(&mut self, n: usize) {
+        self.0.extend((n as u64).to_le_bytes());
+    }
+
+    fn str(&mut self, s: &str) {
+        self.usize(s.len());
+        self.0.extend(s.as_bytes());
+    }
+
+    fn span(&mut self, span: (usize, usize)) {
+        self.usize(span.0);
+        self.usize(span.1);
+    }
+
+    fn list<T>(&mut self, items: &[T], mut item: impl FnMut(&mut Self, &T)) {
+        self.usize(items.len());
+
+        for i in items {
+            item(self, i);
+        }
+    }
+
+    fn annotation(&mut self, annotation: &Option<Annotation>) {
+        let annotation = match annotation {
+            Some(annotation) => annotation,
+            None => return self.u8(0),
+        };
+
+        let tag = match &annotation.ty {
+            Type::Any => 1,
+            Type::Number => 2,
+            Type::String => 3,
+            Type::Bool => 4,
+            Type::Nil => 5,
+            Type::Function => 6,
+            Type::Tuple => 7,
+            Type::Enum(_) => 8,
+        };
+
+        self.u8(tag);
+        if let Type::Enum(name) = &annotation.ty {
+            self.str(name);
+        }
+        self.span(annotation.place);
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Nil => self.u8(0),
+            Value::Boolean(b) => {
+                self.u8(1);
+                self.u8(*b as u8);
+            }
+            Value::Number(n) => {
+                self.u8(2);
+                self.0.extend(n.to_le_bytes());
+            }
+            Value::String(s) => {
+                self.u8(3);
+                self.str(s);
+            }
+            Value::Tuple(values) => {
+                self.u8(4);
+                self.list(values, Self::value);
+            }
+            Value::Enum(ty) => {
+                self.u8(5);
+                self.str(&ty.name);
+                self.list(&ty.variants, |w, (name, fields)| {
+                    w.str(name);
+                    w.usize(*fields);
+                });
+            }
+            other => unreachable!("{} is never a constant", other),
+        }
+    }
+
+    fn op(&mut self, op: &Op) {
+        let (tag, operand) = match *op {
+            Op::Constant(n) => (0, n),
+            Op::Nil => (1, 0),
+            Op::Pop => (2, 0),
+            Op::GetLocal(n) => (3, n),
+            Op::SetLocal(n) => (4, n),
+            Op::GetUpvalue(n) => (5, n),
+            Op::SetUpvalue(n) => (6, n),
+            Op::GetGlobal(n) => (7, n),
+            Op::SetGlobal(n) => (8, n),
+            Op::DefineGlobal(n) => (9, n),
+            Op::Negate => (10, 0),
+            Op::Not => (11, 0),
+            Op::Add => (12, 0),
+            Op::Subtract => (13, 0),
+            Op::Multiply => (14, 0),
+            Op::Divide => (15, 0),
+            Op::Remainder => (16, 0),
+            Op::Equal => (17, 0),
+            Op::NotEqual => (18, 0),
+            Op::Greater => (19, 0),
+            Op::GreaterEqual => (20, 0),
+            Op::Less => (21, 0),
+            Op::LessEqual => (22, 0),
+            Op::Jump(n) => (23, n),
+            Op::JumpIfFalse(n) => (24, n),
+            Op::JumpIfTrue(n) => (25, n),
+            Op::Call(n) => (26, n),
+            Op::Closure(n) => (27, n),
+            Op::CloseUpvalue => (28, 0),
+            Op::Return => (29, 0),
+            Op::Print => (30, 0),
+            Op::Tuple(n) => (31, n),
+            Op::Unpack(n) => (32, n),
+            Op::Get(n) => (33, n),
+            Op::Enum(n) => (34, n),
+            Op::Pattern(n) => (35, n),
+            Op::Destructure => (36, 0),
+            Op::TailCall(n) => (37, n),
+        };
+
+        self.u8(tag);
+        self.u32(operand);
+    }
+
+    fn proto(&mut self, proto: &Proto) {
+        self.str(&proto.name);
+        self.list(&proto.params, Self::annotation);
+        self.annotation(&proto.returns);
+        self.list(&proto.upvalues, |w, (local, index)| {
+            w.u8(*local as u8);
+            w.u32(*index);
+        });
+
+        let chunk = &proto.chunk;
+        self.list(&chunk.code, Self::op);
+        self.list(&chunk.spans, |w, span| w.span(*span));
+        self.list(&chunk.constants, Self::value);
+        self.list(&chunk.functions, |w, f| w.proto(f));
+        self.list(&chunk.calls, |w, call| {
+            w.list(&call.args, |w, span| w.span(*span));
+            w.span(call.place);
+        });
+        self.list(&chunk.patterns, |w, pattern| {
+            w.str(&pattern.ty);
+            w.str(&pattern.variant);
+            w.span(pattern.variant_place);
+            w.usize(pattern.bindings);
+        });
+    }
+}
+
+struct Reader<'b> {
+    bytes: &'b [u8],
+    at: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8], String> {
+        let bytes = self
+            .bytes
+            .get(self.at..self.at.saturating_add(n))
+            .ok_or("Unexpected end of the file.")?;
+
+        self.at += n;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn usize(&mut self) -> Result<usize, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()) as usize)
+    }
+
+    fn str(&mut self) -> Result<String, String> {
+        let len = self.usize()?;
+
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| "Invalid string.".into())
+    }
+
+    fn span(&mut self) -> Result<(usize, usize), String> {
+        Ok((self.usize()?, self.usize()?))
+    }
+
+    fn list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, String>,
+    ) -> Result<Vec<T>, String> {
+        let len = self.usize()?;
+        // Every item takes at least a byte, a corrupt length can't allocate much
+        let mut items = Vec::with_capacity(len.min(self.bytes.len() - self.at));
+
+        for _ in 0..len {
+            items.push(item(self)?);
+        }
+
+        Ok(items)
+    }
+
+    fn annotation(&mut self) -> Result<Option<Annotation>, String> {
+        let ty = match self.u8()? {
+            0 => return Ok(None),
+            1 => Type::Any,
+            2 => Type::Number,
+            3 => Type::String,
+            4 => Type::Bool,
+            5 => Type::Nil,
+            6 => Type::Function,
+            7 => Type::Tuple,
+            8 => Type::Enum(self.str()?),
+            tag => return Err(format!("Invalid type {}.", tag)),
+        };
+
+        Ok(Some(Annotation {
+            ty,
+            place: self.span()?,
+        }))
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        Ok(match self.u8()? {
+            0 => Value::Nil,
+            1 => Value::Boolean(self.u8()? != 0),
+            2 => Value::Number(f64::from_le_bytes(self.take(8)?.try_into().unwrap())),
+            3 => Value::String(self.str()?.into()),
+            4 => Value::Tuple(self.list(Self::value)?.into()),
+            5 => Value::Enum(Rc::new(Enum {
+                name: self.str()?,
+                variants: self.list(|r| Ok((r.str()?, r.usize()?)))?,
+            })),
+            tag => return Err(format!("Invalid constant {}.", tag)),
+        })
+    }
+
+    fn op(&mut self) -> Result<Op, String> {
+        let tag = self.u8()?;
+        let n = self.u32()?;
+
+        Ok(match tag {
+            0 => Op::Constant(n),
+            1 => Op::Nil,
+            2 => Op::Pop,
+            3 => Op::GetLocal(n),
+            4 => Op::SetLocal(n),
+            5 => Op::GetUpvalue(n),
+            6 => Op::SetUpvalue(n),
+            7 => Op::GetGlobal(n),
+            8 => Op::SetGlobal(n),
+            9 => Op::DefineGlobal(n),
+            10 => Op::Negate,
+            11 => Op::Not,
+            12 => Op::Add,
+            13 => Op::Subtract,
+            14 => Op::Multiply,
+            15 => Op::Divide,
+            16 => Op::Remainder,
+            17 => Op::Equal,
+            18 => Op::NotEqual,
+            19 => Op::Greater,
+            20 => Op::GreaterEqual,
+            21 => Op::Less,
+            22 => Op::LessEqual,
+            23 => Op::Jump(n),
+            24 => Op::JumpIfFalse(n),
+            25 => Op::JumpIfTrue(n),
+            26 => Op::Call(n),
+            27 => Op::Closure(n),
+            28 => Op::CloseUpvalue,
+            29 => Op::Return,
+            30 => Op::Print,
+            31 => Op::Tuple(n),
+            32 => Op::Unpack(n),
+            33 => Op::Get(n),
+            34 => Op::Enum(n),
+            35 => Op::Pattern(n),
+            36 => Op::Destructure,
+            37 => Op::TailCall(n),
+            tag => return Err(format!("Invalid instruction {}.", tag)),
+        })
+    }
+
+    fn proto(&mut self) -> Result<Proto, String> {
+        Ok(Proto {
+            name: self.str()?,
+            params: self.list(Self::annotation)?,
+            returns: self.annotation()?,
+            upvalues: self.list(|r| Ok((r.u8()? != 0, r.u32()?)))?,
+            chunk: Chunk {
+                code: self.list(Self::op)?,
+                spans: self.list(Self::span)?,
+                constants: self.list(Self::value)?,
+                functions: self.list(|r| r.proto().map(Rc::new))?,
+                calls: self.list(|r| {
+                    Ok(CallSite {
+                        args: r.list(Self::span)?,
+                        place: r.span()?,
+                    })
+                })?,
+                patterns: self.list(|r| {
+                    Ok(PatternInfo {
+                        ty: r.str()?,
+                        variant: r.str()?,
+                        variant_place: r.span()?,
+                        bindings: r.usize()?,
+                    })
+                })?,
+            },
+        })
+    }
+}
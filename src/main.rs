@@ -1,71 +1,513 @@
-mod environment;
-mod errors;
-mod expr;
-mod interpreter;
-mod parser;
-mod scanner;
-mod statements;
-mod token;
-
-use crate::{errors::error, interpreter::Interpreter, parser::Parser, scanner::*};
+use lux::{
+    checker::Checker,
+    errors::{error, CompileError},
+    exhaustiveness,
+    expr::{Ast, NumericPolicy},
+    gc::Stats,
+    interpreter::{self, Interpreter, DEFAULT_MAX_DEPTH},
+    lints::{Lint, Linter, Warnings},
+    luxc::{self, Program},
+    natives::{Capabilities, Capability},
+    optimizer,
+    parser::Parser,
+    resolver::Resolver,
+    scanner::*,
+    statements::Statement,
+    symbol::Symbol,
+    vm::Vm,
+};
 
 use std::{
-    cmp::Ordering,
+    collections::HashSet,
     env,
-    fs::read_to_string,
+    fs::{self, read_to_string},
     io::{stdin, stdout, Write},
+    path::{Path, PathBuf},
+    process::exit,
+    rc::Rc,
+    thread,
+    time::Duration,
 };
 
-type Error = Box<dyn std::error::Error>;
+type Error = Box<dyn std::error::Error + Send + Sync>;
 
-fn main() -> Result<(), Error> {
-    let args: Vec<_> = env::args().collect();
+const USAGE: &str = "Usage: lux [--strict] [--strict-types] [--strict-numbers] [--backend=tree|vm]
+                  [--max-depth=<calls>] [--max-memory=<bytes>[K|M|G]] [--max-steps=<steps>] [--timeout=<ms>]
+                  [--gc-stats] [-W[no-]<lint>] [-Werror]
+                  [--allow-read[=<dir>]] [--allow-write[=<dir>]] [--allow-env] [--allow-run] [--allow-all]
+                  [script]
+       lux check [-W[no-]<lint>] [-Werror] <script>
+       lux compile [-W[no-]<lint>] [-Werror] <script> [-o <script.luxc>]";
+
+/// Command line flags.
+struct Options {
+    /// Check type annotations at runtime, `--strict-types`
+    strict_types: bool,
+    /// Use strict arithmetic, `--strict-numbers`. `--strict` turns on both.
+    strict_numbers: bool,
+    warnings: Warnings,
+    backend: Backend,
+    /// How many calls can be nested, `--max-depth=`
+    max_depth: usize,
+    /// The memory budget of the script, `--max-memory=`
+    max_memory: Option<usize>,
+    /// How many steps the script can take, `--max-steps=`
+    max_steps: Option<u64>,
+    /// How long the script can run, `--timeout=` in milliseconds
+    timeout: Option<Duration>,
+    /// What the natives can do, `--allow-*`
+    capabilities: Capabilities,
+    /// Print what the cycle collector did after running, `--gc-stats`
+    gc_stats: bool,
+    /// Where `lux compile` writes, `-o`
+    output: Option<String>,
+}
 
-    match args.len().cmp(&2) {
-        Ordering::Less => run_prompt()?,
-        Ordering::Equal => run_file(&args[1])?,
-        Ordering::Greater => todo!(),
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            strict_types: false,
+            strict_numbers: false,
+            warnings: Warnings::default(),
+            backend: Backend::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_memory: None,
+            max_steps: None,
+            timeout: None,
+            capabilities: Capabilities::default(),
+            gc_stats: false,
+            output: None,
+        }
     }
+}
 
-    Ok(())
+/// What runs the scripts, `--backend=`
+#[derive(Default)]
+enum Backend {
+    /// The tree walking interpreter
+    #[default]
+    Tree,
+    /// The bytecode compiler and virtual machine
+    Vm,
 }
 
-fn run_lines(file_name: &str, scanner: &mut Scanner, interpreter: &mut Interpreter) {
-    match scanner.scan_tokens() {
-        Ok(tokens) => {
-            let mut parser = Parser::new(tokens);
+enum Runtime {
+    Tree(Interpreter),
+    Vm(Vm),
+}
+
+impl Runtime {
+    fn interpret(&mut self, statements: &[Statement], ast: &Rc<Ast>) -> Result<(), CompileError> {
+        match self {
+            Runtime::Tree(interpreter) => interpreter.interpret(statements, ast),
+            Runtime::Vm(vm) => vm.interpret(statements, ast),
+        }
+    }
+
+    fn gc_stats(&self) -> Stats {
+        match self {
+            Runtime::Tree(interpreter) => interpreter.gc_stats(),
+            Runtime::Vm(vm) => vm.gc_stats(),
+        }
+    }
+}
 
-            match parser.parse() {
-                Ok(expr) => {
-                    if let Err(e) = interpreter.interpret(&expr) {
-                        error(file_name, scanner.source_raw, &[e]);
+fn main() -> Result<(), Error> {
+    let mut options = Options::default();
+    let mut positional = Vec::new();
+
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => match args.next() {
+                Some(output) => options.output = Some(output),
+                None => {
+                    eprintln!("Missing the file after -o\n{}", USAGE);
+                    exit(64);
+                }
+            },
+            "--strict" => {
+                options.strict_types = true;
+                options.strict_numbers = true;
+            }
+            "--strict-types" => options.strict_types = true,
+            "--strict-numbers" => options.strict_numbers = true,
+            "--gc-stats" => options.gc_stats = true,
+            "--allow-read" => options.capabilities = options.capabilities.allow(Capability::Read),
+            "--allow-write" => options.capabilities = options.capabilities.allow(Capability::Write),
+            "--allow-env" => options.capabilities = options.capabilities.allow(Capability::Env),
+            "--allow-run" => options.capabilities = options.capabilities.allow(Capability::Process),
+            "--allow-all" => options.capabilities = Capabilities::all(),
+            flag if flag.starts_with("--allow-read=") => {
+                options.capabilities = options
+                    .capabilities
+                    .allow_read(&flag["--allow-read=".len()..]);
+            }
+            flag if flag.starts_with("--allow-write=") => {
+                options.capabilities = options
+                    .capabilities
+                    .allow_write(&flag["--allow-write=".len()..]);
+            }
+            "--backend=tree" => options.backend = Backend::Tree,
+            "--backend=vm" => options.backend = Backend::Vm,
+            flag if flag.starts_with("--max-memory=") => {
+                match parse_bytes(&flag["--max-memory=".len()..]) {
+                    Some(bytes) => options.max_memory = Some(bytes),
+                    None => {
+                        eprintln!("Invalid memory limit: {}\n{}", flag, USAGE);
+                        exit(64);
                     }
                 }
-                Err(e) => error(file_name, scanner.source_raw, &[e]),
             }
+            flag if flag.starts_with("--max-steps=") => {
+                match flag["--max-steps=".len()..].parse() {
+                    Ok(steps) => options.max_steps = Some(steps),
+                    Err(_) => {
+                        eprintln!("Invalid number of steps: {}\n{}", flag, USAGE);
+                        exit(64);
+                    }
+                }
+            }
+            flag if flag.starts_with("--timeout=") => match flag["--timeout=".len()..].parse() {
+                Ok(ms) => options.timeout = Some(Duration::from_millis(ms)),
+                Err(_) => {
+                    eprintln!("Invalid timeout: {}\n{}", flag, USAGE);
+                    exit(64);
+                }
+            },
+            "-W" => options.warnings.enabled.extend(Lint::ALL),
+            "-Werror" => options.warnings.fatal = true,
+            flag if flag.starts_with("--max-depth=") => {
+                match flag["--max-depth=".len()..].parse() {
+                    Ok(depth) => options.max_depth = depth,
+                    Err(_) => {
+                        eprintln!("Invalid depth: {}\n{}", flag, USAGE);
+                        exit(64);
+                    }
+                }
+            }
+            flag if flag.starts_with("-W") => {
+                let (name, enable) = match flag[2..].strip_prefix("no-") {
+                    Some(name) => (name, false),
+                    None => (&flag[2..], true),
+                };
+
+                match Lint::from_name(name) {
+                    Some(lint) if enable => options.warnings.enabled.insert(lint),
+                    Some(lint) => options.warnings.enabled.remove(&lint),
+                    None => {
+                        eprintln!("Unknown warning: {}\n{}", name, USAGE);
+                        exit(64);
+                    }
+                };
+            }
+            flag if flag.starts_with('-') => {
+                eprintln!("Unknown flag: {}\n{}", flag, USAGE);
+                exit(64);
+            }
+            _ => positional.push(arg),
+        }
+    }
+
+    // The tree walking interpreter recurses on the host stack for every call,
+    // the virtual machine keeps its frames on the heap
+    let stack = match options.backend {
+        Backend::Tree => interpreter::stack_size(options.max_depth),
+        Backend::Vm => 8 * 1024 * 1024,
+    };
+
+    let max_depth = options.max_depth;
+    let interpreter = thread::Builder::new()
+        .stack_size(stack)
+        .spawn(move || dispatch(&positional, &options));
+
+    match interpreter {
+        Ok(interpreter) => interpreter.join().expect("the interpreter thread panicked"),
+        Err(e) => {
+            eprintln!(
+                "Can't reserve {} MB of stack for --max-depth={}: {}",
+                stack / (1024 * 1024),
+                max_depth,
+                e
+            );
+            exit(70);
         }
+    }
+}
+
+fn dispatch(positional: &[String], options: &Options) -> Result<(), Error> {
+    match positional {
+        [] => run_prompt(options)?,
+        [command, file] if command == "check" => check_file(file, options)?,
+        [command, file] if command == "compile" => compile_file(file, options)?,
+        [file] => run_file(file, options)?,
+        _ => {
+            eprintln!("{}", USAGE);
+            exit(64);
+        }
+    }
+
+    Ok(())
+}
+
+/// `1024`, `64K`, `16M` or `1G`.
+fn parse_bytes(size: &str) -> Option<usize> {
+    let (digits, unit) = match size.char_indices().last()? {
+        (i, 'K' | 'k') => (&size[..i], 1 << 10),
+        (i, 'M' | 'm') => (&size[..i], 1 << 20),
+        (i, 'G' | 'g') => (&size[..i], 1 << 30),
+        _ => (size, 1),
+    };
+
+    digits.parse::<usize>().ok()?.checked_mul(unit)
+}
 
-        Err(errors) => error(file_name, scanner.source_raw, &errors),
+fn numeric_policy(options: &Options) -> NumericPolicy {
+    if options.strict_numbers {
+        NumericPolicy::Strict
+    } else {
+        NumericPolicy::Permissive
     }
 }
 
-fn run(file_name: &str, source: &str) {
-    let mut scanner = Scanner::new(source);
-    let mut interpreter = Interpreter::default();
+fn new_runtime(options: &Options) -> Runtime {
+    match options.backend {
+        Backend::Tree => Runtime::Tree(new_interpreter(options)),
+        Backend::Vm => Runtime::Vm(new_vm(options)),
+    }
+}
 
-    run_lines(file_name, &mut scanner, &mut interpreter)
+fn new_interpreter(options: &Options) -> Interpreter {
+    let mut interpreter = Interpreter::default()
+        .with_type_checks(options.strict_types)
+        .with_capabilities(options.capabilities.clone())
+        .with_numeric_policy(numeric_policy(options))
+        .with_max_depth(options.max_depth)
+        .with_stack_size(interpreter::stack_size(options.max_depth));
+
+    if let Some(bytes) = options.max_memory {
+        interpreter = interpreter.with_memory_limit(bytes);
+    }
+    if let Some(steps) = options.max_steps {
+        interpreter = interpreter.with_max_steps(steps);
+    }
+    if let Some(timeout) = options.timeout {
+        interpreter = interpreter.with_timeout(timeout);
+    }
+
+    interpreter
 }
 
-fn run_file(file: &str) -> Result<(), Error> {
+fn new_vm(options: &Options) -> Vm {
+    let mut vm = Vm::default()
+        .with_type_checks(options.strict_types)
+        .with_capabilities(options.capabilities.clone())
+        .with_numeric_policy(numeric_policy(options))
+        .with_max_depth(options.max_depth);
+
+    if let Some(bytes) = options.max_memory {
+        vm = vm.with_memory_limit(bytes);
+    }
+    if let Some(steps) = options.max_steps {
+        vm = vm.with_max_steps(steps);
+    }
+    if let Some(timeout) = options.timeout {
+        vm = vm.with_timeout(timeout);
+    }
+
+    vm
+}
+
+/// Reports the enabled warnings, returns whether the script can still run.
+fn warn(
+    file_name: &str,
+    source: &str,
+    statements: &[Statement],
+    ast: &Ast,
+    options: &Options,
+) -> bool {
+    let mut warnings = exhaustiveness::check(statements);
+    warnings.extend(Linter::check(statements, ast));
+    warnings.sort_by_key(|w| match w {
+        CompileError::Warning(place, ..) => *place,
+        _ => (0, 0),
+    });
+
+    let warnings = options.warnings.filter(source, warnings);
+    error(file_name, source, &warnings);
+
+    !options.warnings.fatal || warnings.is_empty()
+}
+
+/// Runs `source`, which can use the enums in `types` that earlier code
+/// declared. Returns false if it was rejected because of `-Werror`.
+fn run_lines(
+    file_name: &str,
+    source: &str,
+    types: &mut HashSet<Symbol>,
+    runtime: &mut Runtime,
+    options: &Options,
+) -> bool {
+    let mut ast = Ast::default();
+    let mut parser = Parser::new(Scanner::new(source), &mut ast);
+
+    match parser.parse() {
+        Ok(mut expr) => {
+            if let Err(errors) = Resolver::resolve_with_types(&mut expr, &mut ast, types) {
+                error(file_name, source, &errors);
+                return true;
+            }
+
+            if !warn(file_name, source, &expr, &ast, options) {
+                return false;
+            }
+
+            optimizer::optimize(&mut expr, &mut ast);
+
+            // Kept by the functions it declares, for as long as they are
+            if let Err(e) = runtime.interpret(&expr, &Rc::new(ast)) {
+                error(file_name, source, &[e]);
+            }
+        }
+        Err(errors) => error(file_name, source, &errors),
+    }
+
+    true
+}
+
+fn run(file_name: &str, source: &str, options: &Options) {
+    let mut runtime = new_runtime(options);
+
+    if !run_lines(
+        file_name,
+        source,
+        &mut HashSet::new(),
+        &mut runtime,
+        options,
+    ) {
+        exit(1);
+    }
+
+    if options.gc_stats {
+        print_gc_stats(runtime.gc_stats());
+    }
+}
+
+fn print_gc_stats(stats: Stats) {
+    eprintln!(
+        "gc: {} collections, {} objects reclaimed, {} live",
+        stats.collections, stats.reclaimed, stats.live
+    );
+}
+
+fn run_file(file: &str, options: &Options) -> Result<(), Error> {
+    let bytes = fs::read(file)?;
+
+    if luxc::is_compiled(&bytes) {
+        run_compiled(file, &bytes, options);
+    } else {
+        run(file, &String::from_utf8(bytes)?, options);
+    }
+
+    Ok(())
+}
+
+/// Runs a `.luxc` file, always with the virtual machine.
+fn run_compiled(file: &str, bytes: &[u8], options: &Options) {
+    let mut vm = new_vm(options);
+
+    let program = match luxc::read(bytes).and_then(|p| vm.link(&p.globals).map(|_| p)) {
+        Ok(program) => program,
+        Err(msg) => {
+            eprintln!("Can't load {}: {}", file, msg);
+            exit(65);
+        }
+    };
+
+    if let Err(e) = vm.execute(program.script) {
+        error(&program.file, &program.source, &[e]);
+    }
+
+    if options.gc_stats {
+        print_gc_stats(vm.gc_stats());
+    }
+}
+
+/// Compiles a script to a `.luxc` file, next to it unless `-o` says otherwise.
+fn compile_file(file: &str, options: &Options) -> Result<(), Error> {
+    let source = read_to_string(file)?;
+    let mut ast = Ast::default();
+
+    let statements = match Parser::new(Scanner::new(&source), &mut ast).parse() {
+        Ok(mut statements) => match Resolver::resolve(&mut statements, &mut ast) {
+            Ok(()) => Ok(statements),
+            Err(errors) => Err(errors),
+        },
+        Err(errors) => Err(errors),
+    };
+
+    let mut statements = match statements {
+        Ok(statements) if warn(file, &source, &statements, &ast, options) => statements,
+        Ok(_) => exit(1),
+        Err(errors) => {
+            error(file, &source, &errors);
+            exit(1);
+        }
+    };
+
+    optimizer::optimize(&mut statements, &mut ast);
+
+    let mut vm = Vm::default();
+    let script = vm.compile(&statements, &ast);
+
+    let program = Program {
+        file: file.into(),
+        source,
+        globals: vm.globals().to_vec(),
+        script,
+    };
+
+    let output = match &options.output {
+        Some(output) => PathBuf::from(output),
+        None => Path::new(file).with_extension("luxc"),
+    };
+
+    fs::write(output, luxc::write(&program))?;
+
+    Ok(())
+}
+
+/// Reports type errors and warnings without running the script.
+fn check_file(file: &str, options: &Options) -> Result<(), Error> {
     let source = read_to_string(file)?;
-    run(file, &source);
+    let mut ast = Ast::default();
+
+    let errors = match Parser::new(Scanner::new(&source), &mut ast).parse() {
+        Ok(mut statements) => match Resolver::resolve(&mut statements, &mut ast) {
+            Ok(()) if warn(file, &source, &statements, &ast, options) => {
+                Checker::check(&statements, &ast)
+            }
+            Ok(()) => exit(1),
+            Err(errors) => errors,
+        },
+        Err(errors) => errors,
+    };
+
+    error(file, &source, &errors);
+
+    if !errors.is_empty() {
+        exit(1);
+    }
 
     Ok(())
 }
 
-fn run_prompt() -> Result<(), Error> {
+fn run_prompt(options: &Options) -> Result<(), Error> {
     let mut line = String::new();
-    let mut interpreter = Interpreter::default();
+    let mut runtime = new_runtime(options);
+    // Enums declared on a line can be used in annotations on the next ones
+    let mut types = HashSet::new();
     let stdin = stdin();
 
     loop {
@@ -73,9 +515,13 @@ fn run_prompt() -> Result<(), Error> {
         stdout().flush()?;
 
         match stdin.read_line(&mut line) {
+            // End of input
+            Ok(0) => {
+                println!();
+                return Ok(());
+            }
             Ok(_) => {
-                let mut scanner = Scanner::new(&line);
-                run_lines("repl", &mut scanner, &mut interpreter);
+                run_lines("repl", &line, &mut types, &mut runtime, options);
                 line.clear();
             }
 
@@ -0,0 +1,246 @@
+use crate::{
+    expr::{NativeFn, Value},
+    gc::Heap,
+};
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+    rc::Rc,
+    time::SystemTime,
+};
+
+/// The functions every program starts with: name, arity and body.
+pub const NATIVES: [(&str, usize, NativeFn); 7] = [
+    ("clock", 0, |cx, _| {
+        cx.capabilities.check(Capability::Clock)?;
+
+        Ok(Value::Number(
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as f64,
+        ))
+    }),
+    ("same", 2, |_, args| {
+        Ok(Value::Boolean(args[0].same(&args[1])))
+    }),
+    // How many objects a collection frees depends on the backend
+    ("gc", 0, |cx, _| Ok(Value::Number(cx.heap.collect() as f64))),
+    ("read", 1, |cx, args| {
+        let path = cx.capabilities.check_path(Capability::Read, &args[0])?;
+
+        fs::read_to_string(&path)
+            .map(|text| Value::String(text.into()))
+            .map_err(|e| format!("Can't read {}: {}.", path.display(), e))
+    }),
+    ("write", 2, |cx, args| {
+        let path = cx.capabilities.check_path(Capability::Write, &args[0])?;
+
+        fs::write(&path, args[1].to_string())
+            .map(|_| Value::Nil)
+            .map_err(|e| format!("Can't write {}: {}.", path.display(), e))
+    }),
+    ("env", 1, |cx, args| {
+        cx.capabilities.check(Capability::Env)?;
+
+        match &args[0] {
+            Value::String(name) => {
+                Ok(env::var(&**name).map_or(Value::Nil, |value| Value::String(value.into())))
+            }
+            other => Err(format!(
+                "The name of a variable is a string, got {}.",
+                other
+            )),
+        }
+    }),
+    ("exec", 1, |cx, args| {
+        cx.capabilities.check(Capability::Process)?;
+
+        let command = match &args[0] {
+            Value::String(command) => command,
+            other => return Err(format!("A command is a string, got {}.", other)),
+        };
+
+        // No shell, the words are the program and its arguments
+        let mut words = command.split_whitespace();
+        let program = words.next().ok_or("The command is empty.")?;
+
+        let output = Command::new(program)
+            .args(words)
+            .output()
+            .map_err(|e| format!("Can't run {}: {}.", command, e))?;
+
+        Ok(Value::String(Rc::new(
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+        )))
+    }),
+];
+
+/// What a native function can use besides its arguments.
+pub struct Context<'r> {
+    pub heap: &'r mut Heap,
+    pub capabilities: &'r Capabilities,
+}
+
+/// Something natives can only do when the script is allowed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    Read,
+    Write,
+    Env,
+    Process,
+    Clock,
+}
+
+impl Capability {
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::Read => "fs.read",
+            Capability::Write => "fs.write",
+            Capability::Env => "env",
+            Capability::Process => "process",
+            Capability::Clock => "clock",
+        }
+    }
+}
+
+/// The directories a script can use, or all of them.
+#[derive(Debug, Clone, Default)]
+struct Paths {
+    anywhere: bool,
+    dirs: Vec<PathBuf>,
+}
+
+impl Paths {
+    fn allows(&self, path: &Path) -> bool {
+        self.anywhere || self.dirs.iter().any(|dir| path.starts_with(resolve(dir)))
+    }
+}
+
+/// What the natives of a script are allowed to do. Scripts can only read the
+/// clock by default:
+///
+/// ```
+/// use lux::natives::{Capabilities, Capability};
+///
+/// let sandbox = Capabilities::none()
+///     .allow_read("data")
+///     .allow(Capability::Env);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Capabilities {
+    read: Paths,
+    write: Paths,
+    env: bool,
+    process: bool,
+    clock: bool,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self::none().allow(Capability::Clock)
+    }
+}
+
+impl Capabilities {
+    pub fn none() -> Self {
+        Self {
+            read: Paths::default(),
+            write: Paths::default(),
+            env: false,
+            process: false,
+            clock: false,
+        }
+    }
+
+    pub fn all() -> Self {
+        Self::none()
+            .allow(Capability::Read)
+            .allow(Capability::Write)
+            .allow(Capability::Env)
+            .allow(Capability::Process)
+            .allow(Capability::Clock)
+    }
+
+    /// Grants the capability, for the filesystem anywhere.
+    pub fn allow(mut self, capability: Capability) -> Self {
+        match capability {
+            Capability::Read => self.read.anywhere = true,
+            Capability::Write => self.write.anywhere = true,
+            Capability::Env => self.env = true,
+            Capability::Process => self.process = true,
+            Capability::Clock => self.clock = true,
+        }
+        self
+    }
+
+    /// Allows reading the files in `dir` and its subdirectories.
+    pub fn allow_read(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.read.dirs.push(dir.into());
+        self
+    }
+
+    /// Allows writing the files in `dir` and its subdirectories.
+    pub fn allow_write(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.write.dirs.push(dir.into());
+        self
+    }
+
+    pub fn check(&self, capability: Capability) -> Result<(), String> {
+        let allowed = match capability {
+            Capability::Read => self.read.anywhere,
+            Capability::Write => self.write.anywhere,
+            Capability::Env => self.env,
+            Capability::Process => self.process,
+            Capability::Clock => self.clock,
+        };
+
+        if allowed {
+            Ok(())
+        } else {
+            Err(format!("Permission denied: {}.", capability.name()))
+        }
+    }
+
+    /// The file at `path` if the capability allows using it, links and `..`
+    /// are resolved before looking at the allowed directories.
+    pub fn check_path(&self, capability: Capability, path: &Value) -> Result<PathBuf, String> {
+        let path = match path {
+            Value::String(path) => resolve(Path::new(&**path)),
+            other => return Err(format!("A path is a string, got {}.", other)),
+        };
+
+        let paths = match capability {
+            Capability::Write => &self.write,
+            _ => &self.read,
+        };
+
+        if paths.allows(&path) {
+            Ok(path)
+        } else {
+            Err(format!(
+                "Permission denied: {} for {}.",
+                capability.name(),
+                path.display()
+            ))
+        }
+    }
+}
+
+/// The absolute path without links, files that don't exist yet are resolved
+/// through their directory.
+fn resolve(path: &Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) if parent.as_os_str().is_empty() => {
+            resolve(Path::new(".")).join(name)
+        }
+        (Some(parent), Some(name)) => resolve(parent).join(name),
+        _ => path.to_path_buf(),
+    }
+}
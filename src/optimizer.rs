@@ -0,0 +1,201 @@
+use crate::{
+    expr::{Ast, Expr, ExprId, ExprList, NumericPolicy, Value},
+    statements::Statement,
+    token::{Token, TokenType},
+};
+
+use std::{cmp::Ordering, mem, rc::Rc};
+
+/// Simplifies resolved statements before they run: operations on literals are
+/// computed once, groupings are dropped and branches that can never run are
+/// removed.
+///
+/// Operations that would fail are kept so the error happens at runtime with
+/// its span, and so are the ones that depend on the [`NumericPolicy`]: they
+/// are only folded when the strict policy accepts them, where both policies
+/// agree.
+pub fn optimize(statements: &mut Vec<Statement>, ast: &mut Ast) {
+    for stmt in mem::take(statements) {
+        if let Some(stmt) = statement(stmt, ast) {
+            statements.push(stmt);
+        }
+    }
+}
+
+/// Where a removed statement must still be something.
+fn or_empty(stmt: Statement, ast: &mut Ast) -> Statement {
+    statement(stmt, ast).unwrap_or(Statement::Block(Vec::new()))
+}
+
+/// The simplified statement, `None` if it does nothing.
+fn statement(stmt: Statement, ast: &mut Ast) -> Option<Statement> {
+    let stmt = match stmt {
+        Statement::Print(expr) => Statement::Print(expression(expr, ast)),
+        Statement::Expresion(expr) => Statement::Expresion(expression(expr, ast)),
+        Statement::Var(mut var) => {
+            var.initializer = var.initializer.map(|expr| expression(expr, ast));
+            Statement::Var(var)
+        }
+        Statement::Unpack(names, expr) => Statement::Unpack(names, expression(expr, ast)),
+        Statement::Block(mut statements) => {
+            optimize(&mut statements, ast);
+            Statement::Block(statements)
+        }
+        Statement::If(condition, then_branch, else_branch) => {
+            let condition = expression(condition, ast);
+
+            match ast[condition] {
+                // The branches are statements, not declarations, so they can
+                // take the place of the `if` without changing any scope
+                Expr::Literal(value) if ast[value].is_truthy() => {
+                    return statement(*then_branch, ast)
+                }
+                Expr::Literal(_) => return else_branch.and_then(|branch| statement(*branch, ast)),
+                _ => Statement::If(
+                    condition,
+                    Box::new(or_empty(*then_branch, ast)),
+                    else_branch.map(|branch| Box::new(or_empty(*branch, ast))),
+                ),
+            }
+        }
+        Statement::While(condition, body) => {
+            let condition = expression(condition, ast);
+
+            match ast[condition] {
+                Expr::Literal(value) if !ast[value].is_truthy() => return None,
+                _ => Statement::While(condition, Box::new(or_empty(*body, ast))),
+            }
+        }
+        Statement::Function(mut function) => {
+            optimize(&mut Rc::make_mut(&mut function).body, ast);
+            Statement::Function(function)
+        }
+        Statement::Return(keyword, expr) => Statement::Return(keyword, expression(expr, ast)),
+        Statement::Enum(decl) => Statement::Enum(decl),
+        Statement::Match(keyword, scrutinee, arms) => Statement::Match(
+            keyword,
+            expression(scrutinee, ast),
+            arms.into_iter()
+                .map(|(pattern, arm)| (pattern, or_empty(arm, ast)))
+                .collect(),
+        ),
+    };
+
+    Some(stmt)
+}
+
+/// Simplifies `id` where it is, folded operations become literals with the
+/// span of the whole operation. Returns the node that takes its place, a
+/// grouping is replaced by what it holds and a logical operation by the
+/// operand it gives.
+fn expression(id: ExprId, ast: &mut Ast) -> ExprId {
+    match ast[id] {
+        Expr::Grouping(expr) => expression(expr, ast),
+        Expr::Unary(op, rhs) => {
+            let rhs = expression(rhs, ast);
+
+            let value = match (ast[rhs], &ast[op]._type) {
+                (Expr::Literal(value), TokenType::Minus) => {
+                    ast[value].clone().neg(NumericPolicy::Strict).ok()
+                }
+                (Expr::Literal(value), _) => Some(Value::Boolean(!ast[value].is_truthy())),
+                _ => None,
+            };
+
+            match value {
+                Some(value) => ast.fold(id, value),
+                None => ast[id] = Expr::Unary(op, rhs),
+            }
+
+            id
+        }
+        Expr::Binary(lhs, op, rhs) => {
+            let lhs = expression(lhs, ast);
+            let rhs = expression(rhs, ast);
+
+            let value = match (ast[lhs], ast[rhs]) {
+                (Expr::Literal(a), Expr::Literal(b)) => binary(&ast[a], &ast[op], &ast[b]),
+                _ => None,
+            };
+
+            match value {
+                Some(value) => ast.fold(id, value),
+                None => ast[id] = Expr::Binary(lhs, op, rhs),
+            }
+
+            id
+        }
+        Expr::Logical(lhs, op, rhs) => {
+            let lhs = expression(lhs, ast);
+            let rhs = expression(rhs, ast);
+
+            match ast[lhs] {
+                // `and` and `or` give one of their operands
+                Expr::Literal(value) => {
+                    if ast[value].is_truthy() == (ast[op]._type == TokenType::Or) {
+                        lhs
+                    } else {
+                        rhs
+                    }
+                }
+                _ => {
+                    ast[id] = Expr::Logical(lhs, op, rhs);
+                    id
+                }
+            }
+        }
+        Expr::Assign(name, slot, value) => {
+            let value = expression(value, ast);
+            ast[id] = Expr::Assign(name, slot, value);
+            id
+        }
+        Expr::Call(callee, paren, args) => {
+            let callee = expression(callee, ast);
+            list(args, ast);
+            ast[id] = Expr::Call(callee, paren, args);
+            id
+        }
+        Expr::Get(object, name) => {
+            let object = expression(object, ast);
+            ast[id] = Expr::Get(object, name);
+            id
+        }
+        // Every evaluation creates a new tuple, `same` can tell them apart
+        Expr::Tuple(elements) => {
+            list(elements, ast);
+            id
+        }
+        _ => id,
+    }
+}
+
+fn list(list: ExprList, ast: &mut Ast) {
+    for i in 0..list.len() {
+        let expr = expression(ast[list][i], ast);
+        ast[list][i] = expr;
+    }
+}
+
+/// The result of a binary operation, if it can be computed ahead of time.
+fn binary(lhs: &Value, op: &Token, rhs: &Value) -> Option<Value> {
+    let policy = NumericPolicy::Strict;
+
+    let test: fn(Ordering) -> bool = match op._type {
+        TokenType::Plus => return lhs.clone().add(rhs.clone(), policy).ok(),
+        TokenType::Minus => return lhs.clone().sub(rhs.clone(), policy).ok(),
+        TokenType::Star => return lhs.clone().mul(rhs.clone(), policy).ok(),
+        TokenType::Slash => return lhs.clone().div(rhs.clone(), policy).ok(),
+        TokenType::Percent => return lhs.clone().rem(rhs.clone(), policy).ok(),
+        TokenType::EqualEqual => return Some(Value::Boolean(lhs == rhs)),
+        TokenType::BangEqual => return Some(Value::Boolean(lhs != rhs)),
+        TokenType::Greater => Ordering::is_gt,
+        TokenType::GreaterEqual => Ordering::is_ge,
+        TokenType::Less => Ordering::is_lt,
+        TokenType::LessEqual => Ordering::is_le,
+        _ => return None,
+    };
+
+    let ordering = lhs.compare(rhs).ok()?;
+
+    Some(Value::Boolean(ordering.is_some_and(test)))
+}
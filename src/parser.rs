@@ -1,48 +1,124 @@
 use crate::{
     errors::CompileError,
-    expr::{Expr, Value},
-    statements::Statement,
+    expr::{Ast, Expr, ExprId, TokenId, Value},
+    scanner::Scanner,
+    statements::{EnumDecl, FunctionDecl, Param, Pattern, Statement, VarDecl},
     token::{Token, TokenType},
+    types::{Annotation, Type},
 };
 
-pub type CompResult = Result<Box<Expr>, CompileError>;
+use std::{collections::HashMap, rc::Rc};
 
-pub struct Parser {
-    tokens: Vec<Token>,
+pub type CompResult = Result<ExprId, CompileError>;
+
+/// Parses the tokens of a [`Scanner`] into statements, their expressions are
+/// added to an [`Ast`] that can already hold the ones of earlier code.
+///
+/// Tokens are taken from the scanner as the parser gets to them and moved to
+/// the token table of the tree.
+pub struct Parser<'s, 'a> {
+    tokens: Scanner<'s>,
+    ast: &'a mut Ast,
+    /// The next token, an index in the token table of the tree
     current: usize,
+    /// Doc comments by the index of the token that follows them
+    docs: HashMap<usize, String>,
+    /// What the scanner rejected, the tokens around it are still parsed
+    errors: Vec<CompileError>,
 }
 
-impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+impl<'s, 'a> Parser<'s, 'a> {
+    pub fn new(tokens: Scanner<'s>, ast: &'a mut Ast) -> Self {
+        let mut parser = Self {
+            tokens,
+            current: ast.tokens.len(),
+            ast,
+            docs: HashMap::new(),
+            errors: Vec::new(),
+        };
+
+        parser.pull();
+        parser
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Statement>, CompileError> {
+    /// Every statement, or the errors in the source. Scanner errors come
+    /// first, the parser could have failed because of them.
+    pub fn parse(&mut self) -> Result<Vec<Statement>, Vec<CompileError>> {
         let mut statements = Vec::new();
 
-        while !self.is_at_end() {
-            statements.push(self.declaration()?);
+        let result = loop {
+            if self.is_at_end() {
+                break Ok(statements);
+            }
+
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(e) => break Err(e),
+            }
+        };
+
+        // Everything else the scanner would reject
+        for lexeme in self.tokens.by_ref() {
+            if let Err(e) = lexeme {
+                self.errors.push(e);
+            }
         }
 
-        Ok(statements)
+        match result {
+            _ if !self.errors.is_empty() => Err(std::mem::take(&mut self.errors)),
+            Ok(statements) => Ok(statements),
+            Err(e) => Err(vec![e]),
+        }
+    }
+
+    /// Takes tokens from the scanner until the current one is in the table.
+    fn pull(&mut self) {
+        while self.ast.tokens.len() <= self.current {
+            let lexeme = match self.tokens.next() {
+                Some(Ok(lexeme)) => lexeme,
+                Some(Err(e)) => {
+                    self.errors.push(e);
+                    continue;
+                }
+                None => unreachable!("the parser stops at the end of file"),
+            };
+
+            // Doc comments can be anywhere, only declarations pick them up
+            if lexeme._type == TokenType::DocComment {
+                let doc = self.docs.entry(self.ast.tokens.len());
+
+                doc.and_modify(|doc| {
+                    doc.push('\n');
+                    doc.push_str(lexeme.text);
+                })
+                .or_insert_with(|| lexeme.text.into());
+            } else {
+                self.ast.token(lexeme.into());
+            }
+        }
     }
 
     // Movments
 
     fn peek(&self) -> &Token {
-        &self.tokens[self.current]
+        &self.ast.tokens[self.current]
     }
 
     fn advance(&mut self) -> &Token {
         if !self.is_at_end() {
             self.current += 1;
+            self.pull();
         }
 
         self.previous()
     }
 
     fn previous(&self) -> &Token {
-        &self.tokens[self.current - 1]
+        &self.ast.tokens[self.current - 1]
+    }
+
+    fn previous_id(&self) -> TokenId {
+        TokenId::new(self.current - 1)
     }
 
     // Helpers
@@ -80,11 +156,9 @@ impl Parser {
         let mut expr = self.comparasion()?;
 
         while self.matches(&[TokenType::BangEqual, TokenType::EqualEqual]) {
-            expr = Box::new(Expr::Binary(
-                expr,
-                self.previous().clone(),
-                self.comparasion()?,
-            ));
+            let op = self.previous_id();
+            let rhs = self.comparasion()?;
+            expr = self.ast.push(Expr::Binary(expr, op, rhs));
         }
 
         Ok(expr)
@@ -94,9 +168,9 @@ impl Parser {
         let mut expr = self.and()?;
 
         while self.matches(&[TokenType::Or]) {
-            let op = self.previous().clone();
+            let op = self.previous_id();
             let rhs = self.and()?;
-            expr = Box::new(Expr::Logical(expr, op, rhs));
+            expr = self.ast.push(Expr::Logical(expr, op, rhs));
         }
 
         Ok(expr)
@@ -106,9 +180,9 @@ impl Parser {
         let mut expr = self.equality()?;
 
         while self.matches(&[TokenType::And]) {
-            let op = self.previous().clone();
+            let op = self.previous_id();
             let rhs = self.equality()?;
-            expr = Box::new(Expr::Logical(expr, op, rhs));
+            expr = self.ast.push(Expr::Logical(expr, op, rhs));
         }
 
         Ok(expr)
@@ -123,7 +197,9 @@ impl Parser {
             TokenType::Less,
             TokenType::LessEqual,
         ]) {
-            expr = Box::new(Expr::Binary(expr, self.previous().clone(), self.term()?));
+            let op = self.previous_id();
+            let rhs = self.term()?;
+            expr = self.ast.push(Expr::Binary(expr, op, rhs));
         }
 
         Ok(expr)
@@ -133,7 +209,9 @@ impl Parser {
         let mut expr = self.factor()?;
 
         while self.matches(&[TokenType::Minus, TokenType::Plus]) {
-            expr = Box::new(Expr::Binary(expr, self.previous().clone(), self.factor()?));
+            let op = self.previous_id();
+            let rhs = self.factor()?;
+            expr = self.ast.push(Expr::Binary(expr, op, rhs));
         }
 
         Ok(expr)
@@ -142,8 +220,10 @@ impl Parser {
     fn factor(&mut self) -> CompResult {
         let mut expr = self.unary()?;
 
-        while self.matches(&[TokenType::Slash, TokenType::Star]) {
-            expr = Box::new(Expr::Binary(expr, self.previous().clone(), self.unary()?));
+        while self.matches(&[TokenType::Slash, TokenType::Star, TokenType::Percent]) {
+            let op = self.previous_id();
+            let rhs = self.unary()?;
+            expr = self.ast.push(Expr::Binary(expr, op, rhs));
         }
 
         Ok(expr)
@@ -151,10 +231,9 @@ impl Parser {
 
     fn unary(&mut self) -> CompResult {
         if self.matches(&[TokenType::Bang, TokenType::Minus]) {
-            return Ok(Box::new(Expr::Unary(
-                self.previous().clone(),
-                self.unary()?,
-            )));
+            let op = self.previous_id();
+            let rhs = self.unary()?;
+            return Ok(self.ast.push(Expr::Unary(op, rhs)));
         }
 
         self.call()
@@ -166,6 +245,10 @@ impl Parser {
         loop {
             if self.matches(&[TokenType::LeftParen]) {
                 expr = self.finish_call(expr)?;
+            } else if self.matches(&[TokenType::Dot]) {
+                self.consume(TokenType::Identifier, "Expect property name after '.'.")?;
+                let name = self.previous_id();
+                expr = self.ast.push(Expr::Get(expr, name));
             } else {
                 break;
             }
@@ -177,33 +260,77 @@ impl Parser {
     fn primary(&mut self) -> CompResult {
         // TODO Tidy up this
         if self.matches(&[TokenType::False]) {
-            return Ok(Box::new(Expr::Literal(Value::Boolean(false))));
+            return Ok(self.literal(Value::Boolean(false)));
         }
 
         if self.matches(&[TokenType::True]) {
-            return Ok(Box::new(Expr::Literal(Value::Boolean(true))));
+            return Ok(self.literal(Value::Boolean(true)));
         }
 
         if self.matches(&[TokenType::Nil]) {
-            return Ok(Box::new(Expr::Literal(Value::Nil)));
+            return Ok(self.literal(Value::Nil));
         }
 
         if self.matches(&[TokenType::LeftParen]) {
+            let start = self.previous().place.0;
+
+            if self.matches(&[TokenType::RightParen]) {
+                let place = (start, self.previous().place.1);
+                return Ok(self.ast.literal(Value::Tuple(Rc::new(Vec::new())), place));
+            }
+
             let expr = self.expression()?;
+
+            // `(a, b)` is a tuple and `(a)` just a grouping, `(a,)` is a one element tuple
+            if self.matches(&[TokenType::Comma]) {
+                let mut elements = vec![expr];
+
+                while !self.check(&TokenType::RightParen) {
+                    elements.push(self.expression()?);
+
+                    if !self.matches(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+
+                self.consume(TokenType::RightParen, "Expect ')' after tuple elements.")?;
+                let elements = self.ast.list(elements);
+                return Ok(self.ast.push(Expr::Tuple(elements)));
+            }
+
             self.consume(TokenType::RightParen, "Expect ')' after expression.")?;
 
-            return Ok(Box::new(Expr::Grouping(expr)));
+            return Ok(self.ast.push(Expr::Grouping(expr)));
+        }
+
+        if self.matches(&[TokenType::This]) {
+            let keyword = self.previous_id();
+            return Ok(self.ast.push(Expr::This(keyword)));
+        }
+
+        if self.matches(&[TokenType::Super]) {
+            let keyword = self.previous_id();
+            self.consume(TokenType::Dot, "Expect '.' after 'super'.")?;
+            self.consume(TokenType::Identifier, "Expect superclass method name.")?;
+            let method = self.previous_id();
+
+            return Ok(self.ast.push(Expr::Super(keyword, method)));
         }
 
         if self.matches(&[TokenType::Identifier]) {
-            return Ok(Box::new(Expr::Variable(self.previous().clone())));
+            let name = self.previous_id();
+            return Ok(self.ast.push(Expr::Variable(name, None)));
         }
 
         let tkn = self.peek();
 
-        let expr = match &tkn._type {
-            TokenType::String(s) => Box::new(Expr::Literal(Value::String(s.to_owned()))),
-            TokenType::Number(n) => Box::new(Expr::Literal(Value::Number(*n))),
+        let value = match &tkn._type {
+            TokenType::String => {
+                let (start, end) = tkn.place;
+                let text = &self.tokens.source_raw[start + 1..end - 1];
+                Value::String(Rc::new(text.into()))
+            }
+            TokenType::Number(n) => Value::Number(*n),
             _ => {
                 return Err(CompileError::Parser(
                     tkn.place,
@@ -213,7 +340,13 @@ impl Parser {
         };
 
         self.advance();
-        Ok(expr)
+        Ok(self.literal(value))
+    }
+
+    /// A literal spanning the previous token.
+    fn literal(&mut self, value: Value) -> ExprId {
+        let place = self.previous().place;
+        self.ast.literal(value, place)
     }
 
     fn consume(&mut self, tkn: TokenType, error_msg: &str) -> Result<&Token, CompileError> {
@@ -275,6 +408,10 @@ impl Parser {
             return self.while_statement();
         }
 
+        if self.matches(&[TokenType::Match]) {
+            return self.match_statement();
+        }
+
         if self.matches(&[TokenType::LeftBrace]) {
             return Ok(Statement::Block(self.block()?));
         }
@@ -283,16 +420,24 @@ impl Parser {
     }
 
     fn declaration(&mut self) -> Result<Statement, CompileError> {
+        let doc = self.docs.remove(&self.current);
+
         if self.matches(&[TokenType::Fn]) {
-            self.function("function")
+            self.function("function", doc)
         } else if self.matches(&[TokenType::Var]) {
-            self.var_declaration()
+            self.var_declaration(doc)
+        } else if self.matches(&[TokenType::Enum]) {
+            self.enum_declaration(doc)
         } else {
             self.statement()
         }
     }
 
-    fn function(&mut self, kind: &'static str) -> Result<Statement, CompileError> {
+    fn function(
+        &mut self,
+        kind: &'static str,
+        doc: Option<String>,
+    ) -> Result<Statement, CompileError> {
         let name = self
             .consume(TokenType::Identifier, &format!("Expect {} name", kind))?
             .clone();
@@ -302,56 +447,209 @@ impl Parser {
             &format!("Expect '(' after {}  name.", kind),
         )?;
 
-        let mut parameters = Vec::new();
+        let mut params = Vec::new();
 
         if !self.check(&TokenType::RightParen) {
-            parameters.push(
-                self.consume(TokenType::Identifier, "Expect parameter name.")?
-                    .clone(),
-            );
+            params.push(self.parameter()?);
 
             while self.matches(&[TokenType::Comma]) {
                 // TODO add maximum limit of parameters
-                parameters.push(
-                    self.consume(TokenType::Identifier, "Expect parameter name.")?
-                        .clone(),
-                );
+                params.push(self.parameter()?);
             }
         }
 
         self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
+
+        let returns = if self.matches(&[TokenType::Arrow]) {
+            Some(self.annotation()?)
+        } else {
+            None
+        };
+
         self.consume(
             TokenType::LeftBrace,
             &format!("Expect '{{' before {} body.", kind),
         )?;
 
-        Ok(Statement::Function(name, parameters, self.block()?))
+        Ok(Statement::Function(Rc::new(FunctionDecl {
+            doc,
+            name,
+            params,
+            returns,
+            body: self.block()?,
+        })))
+    }
+
+    fn parameter(&mut self) -> Result<Param, CompileError> {
+        let name = self
+            .consume(TokenType::Identifier, "Expect parameter name.")?
+            .clone();
+
+        let annotation = if self.matches(&[TokenType::Colon]) {
+            Some(self.annotation()?)
+        } else {
+            None
+        };
+
+        Ok(Param { name, annotation })
+    }
+
+    /// A type name, `fn` and `nil` are keywords so they are accepted too.
+    fn annotation(&mut self) -> Result<Annotation, CompileError> {
+        if self.matches(&[TokenType::Identifier, TokenType::Fn, TokenType::Nil]) {
+            let tkn = self.previous();
+
+            Ok(Annotation {
+                ty: Type::from_name(&tkn.lexeme),
+                place: tkn.place,
+            })
+        } else {
+            Err(CompileError::Parser(
+                self.peek().place,
+                "Expect type.".into(),
+            ))
+        }
+    }
+
+    fn enum_declaration(&mut self, doc: Option<String>) -> Result<Statement, CompileError> {
+        let name = self
+            .consume(TokenType::Identifier, "Expect enum name.")?
+            .clone();
+        self.consume(TokenType::LeftBrace, "Expect '{' before enum body.")?;
+
+        let mut variants: Vec<(Token, Vec<Token>)> = Vec::new();
+
+        while !self.check(&TokenType::RightBrace) {
+            let variant = self
+                .consume(TokenType::Identifier, "Expect variant name.")?
+                .clone();
+
+            if variants.iter().any(|(v, _)| v.lexeme == variant.lexeme) {
+                return Err(CompileError::Parser(
+                    variant.place,
+                    format!(
+                        "Already a variant named {} in {}.",
+                        variant.lexeme, name.lexeme
+                    ),
+                ));
+            }
+
+            let fields = if self.matches(&[TokenType::LeftParen]) {
+                self.identifier_list("field")?
+            } else {
+                Vec::new()
+            };
+
+            variants.push((variant, fields));
+
+            if !self.matches(&[TokenType::Comma]) {
+                break;
+            }
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after enum body.")?;
+
+        Ok(Statement::Enum(EnumDecl {
+            doc,
+            name,
+            variants,
+        }))
+    }
+
+    /// Parses `a, b, c)`, the opening parenthesis must be already consumed.
+    fn identifier_list(&mut self, kind: &'static str) -> Result<Vec<Token>, CompileError> {
+        let mut names = Vec::new();
+        let error_msg = format!("Expect {} name.", kind);
+
+        if !self.check(&TokenType::RightParen) {
+            names.push(self.consume(TokenType::Identifier, &error_msg)?.clone());
+
+            while self.matches(&[TokenType::Comma]) {
+                names.push(self.consume(TokenType::Identifier, &error_msg)?.clone());
+            }
+        }
+
+        self.consume(
+            TokenType::RightParen,
+            &format!("Expect ')' after {}s.", kind),
+        )?;
+
+        Ok(names)
     }
 
-    fn var_declaration(&mut self) -> Result<Statement, CompileError> {
+    fn var_declaration(&mut self, doc: Option<String>) -> Result<Statement, CompileError> {
         let name = self
             .consume(TokenType::Identifier, "Expect variable name.")?
             .clone();
 
-        let mut value = Expr::Literal(Value::Nil);
+        if self.check(&TokenType::Comma) {
+            return self.unpack_declaration(name);
+        }
 
-        if self.matches(&[TokenType::Equal]) {
-            value = *self.expression()?;
+        let annotation = if self.matches(&[TokenType::Colon]) {
+            Some(self.annotation()?)
+        } else {
+            None
+        };
+
+        let initializer = if self.matches(&[TokenType::Equal]) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
+
+        Ok(Statement::Var(VarDecl {
+            doc,
+            name,
+            annotation,
+            initializer,
+        }))
+    }
+
+    fn unpack_declaration(&mut self, first: Token) -> Result<Statement, CompileError> {
+        let mut names = vec![first];
+
+        while self.matches(&[TokenType::Comma]) {
+            names.push(
+                self.consume(TokenType::Identifier, "Expect variable name.")?
+                    .clone(),
+            );
         }
 
+        self.consume(TokenType::Equal, "Expect '=' after variable names.")?;
+        let value = self.expression_list()?;
         self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
 
-        Ok(Statement::Var(name, value))
+        Ok(Statement::Unpack(names, value))
+    }
+
+    /// One expression, or a tuple if there are many separated by commas.
+    fn expression_list(&mut self) -> CompResult {
+        let first = self.expression()?;
+
+        if !self.check(&TokenType::Comma) {
+            return Ok(first);
+        }
+
+        let mut elements = vec![first];
+        while self.matches(&[TokenType::Comma]) {
+            elements.push(self.expression()?);
+        }
+
+        let elements = self.ast.list(elements);
+        Ok(self.ast.push(Expr::Tuple(elements)))
     }
 
-    fn assignment(&mut self) -> Result<Box<Expr>, CompileError> {
+    fn assignment(&mut self) -> CompResult {
         let expr = self.or()?;
 
         if self.matches(&[TokenType::Equal]) {
             let value = self.assignment()?;
 
-            if let Expr::Variable(v) = *expr {
-                return Ok(Box::new(Expr::Assign(v, value)));
+            if let Expr::Variable(v, _) = self.ast[expr] {
+                return Ok(self.ast.push(Expr::Assign(v, None, value)));
             }
 
             let equals = self.previous();
@@ -381,7 +679,7 @@ impl Parser {
         let value = self.expression()?;
         self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
 
-        Ok(Statement::Print(*value))
+        Ok(Statement::Print(value))
     }
 
     fn if_statement(&mut self) -> Result<Statement, CompileError> {
@@ -396,7 +694,7 @@ impl Parser {
             None
         };
 
-        Ok(Statement::If(*condition, then_branch, else_branch))
+        Ok(Statement::If(condition, then_branch, else_branch))
     }
 
     fn while_statement(&mut self) -> Result<Statement, CompileError> {
@@ -404,16 +702,57 @@ impl Parser {
         let condition = self.expression()?;
         self.consume(TokenType::RightParen, "Expect ')' after if condition")?;
 
-        Ok(Statement::While(*condition, Box::new(self.statement()?)))
+        Ok(Statement::While(condition, Box::new(self.statement()?)))
+    }
+
+    fn match_statement(&mut self) -> Result<Statement, CompileError> {
+        let keyword = self.previous().clone();
+
+        self.consume(TokenType::LeftParen, "Expect '(' after 'match'.")?;
+        let scrutinee = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after match value.")?;
+        self.consume(TokenType::LeftBrace, "Expect '{' before match arms.")?;
+
+        let mut arms = Vec::new();
+
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            let pattern = if self.matches(&[TokenType::Else]) {
+                Pattern::Wildcard(self.previous().clone())
+            } else {
+                let ty = self
+                    .consume(TokenType::Identifier, "Expect enum name or 'else'.")?
+                    .clone();
+                self.consume(TokenType::Dot, "Expect '.' after enum name.")?;
+                let variant = self
+                    .consume(TokenType::Identifier, "Expect variant name.")?
+                    .clone();
+
+                let bindings = if self.matches(&[TokenType::LeftParen]) {
+                    self.identifier_list("binding")?
+                } else {
+                    Vec::new()
+                };
+
+                Pattern::Variant(ty, None, variant, bindings)
+            };
+
+            self.consume(TokenType::FatArrow, "Expect '=>' after pattern.")?;
+            arms.push((pattern, self.statement()?));
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after match arms.")?;
+
+        Ok(Statement::Match(keyword, scrutinee, arms))
     }
 
     fn for_statement(&mut self) -> Result<Statement, CompileError> {
+        let keyword = self.previous().place;
         self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
 
         let init = if self.matches(&[TokenType::Semicolon]) {
             None
         } else if self.matches(&[TokenType::Var]) {
-            Some(self.var_declaration()?)
+            Some(self.var_declaration(None)?)
         } else {
             Some(self.expression_statement()?)
         };
@@ -436,13 +775,14 @@ impl Parser {
         let mut body = self.statement()?;
 
         if let Some(increment) = increment {
-            body = Statement::Block(vec![body, Statement::Expresion(*increment)]);
+            body = Statement::Block(vec![body, Statement::Expresion(increment)]);
         }
 
-        body = Statement::While(
-            *condition.unwrap_or_else(|| Box::new(Expr::Literal(Value::Boolean(true)))),
-            Box::new(body),
-        );
+        let condition = match condition {
+            Some(condition) => condition,
+            None => self.ast.literal(Value::Boolean(true), keyword),
+        };
+        body = Statement::While(condition, Box::new(body));
 
         if let Some(init) = init {
             body = Statement::Block(vec![init, body]);
@@ -455,15 +795,15 @@ impl Parser {
         let value = self.expression()?;
         self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
 
-        Ok(Statement::Expresion(*value))
+        Ok(Statement::Expresion(value))
     }
 
     fn return_statement(&mut self) -> Result<Statement, CompileError> {
         let token = self.previous().clone();
         let value = if self.check(&TokenType::Semicolon) {
-            Expr::Literal(Value::Nil)
+            self.ast.literal(Value::Nil, token.place)
         } else {
-            *self.expression()?
+            self.expression_list()?
         };
 
         self.consume(TokenType::Semicolon, "Expect ';' after return value.")?;
@@ -471,22 +811,22 @@ impl Parser {
         Ok(Statement::Return(token, value))
     }
 
-    fn finish_call(&mut self, callee: Box<Expr>) -> CompResult {
+    fn finish_call(&mut self, callee: ExprId) -> CompResult {
         let mut arguments = Vec::new();
 
         if !self.check(&TokenType::RightParen) {
-            arguments.push(*self.expression()?);
+            arguments.push(self.expression()?);
 
             while self.matches(&[TokenType::Comma]) {
                 // TODO add maximum limit of arguments
-                arguments.push(*self.expression()?);
+                arguments.push(self.expression()?);
             }
         }
 
-        let paren = self
-            .consume(TokenType::RightParen, "Expect ')' after arguments.")?
-            .clone();
+        self.consume(TokenType::RightParen, "Expect ')' after arguments.")?;
+        let paren = self.previous_id();
+        let arguments = self.ast.list(arguments);
 
-        Ok(Box::new(Expr::Call(callee, paren, arguments)))
+        Ok(self.ast.push(Expr::Call(callee, paren, arguments)))
     }
 }
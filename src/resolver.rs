@@ -0,0 +1,311 @@
+use crate::{
+    errors::CompileError,
+    expr::{Ast, Expr, ExprId, Slot},
+    statements::{Pattern, Statement},
+    symbol::Symbol,
+    token::Token,
+    types::{Annotation, Type},
+};
+
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+/// Static pass that runs between the parser and the interpreter, it binds
+/// every variable to the scope it is declared in, so closures see the
+/// variables that existed where they were written and not the ones declared
+/// afterwards.
+///
+/// It mirrors the environments the interpreter creates: one per block, per
+/// function call (holding the parameters) and per match arm (holding the
+/// bindings). Locals get a [`Slot`], the position of their environment and
+/// their own in it, so the interpreter finds them without looking at names.
+/// Names not found in any of those are globals, looked up by name at runtime.
+///
+/// It also rejects code that can't be right wherever it runs, like
+/// annotations naming a type that isn't declared. Every error is reported
+/// before anything is executed.
+pub struct Resolver<'a> {
+    /// Where the slots of the variables are written
+    ast: &'a mut Ast,
+    /// Every local name, the scopes are innermost last
+    scopes: Vec<HashMap<Symbol, Local>>,
+    /// How many functions enclose the code being resolved
+    functions: usize,
+    /// Annotations naming an enum, checked once every enum is known
+    annotations: Vec<Annotation>,
+    /// The enums declared anywhere in the code
+    enums: Vec<Symbol>,
+    errors: Vec<CompileError>,
+}
+
+/// A variable declared in a scope.
+#[derive(Clone, Copy)]
+struct Local {
+    /// Its position in the environment, the order it was declared in
+    index: usize,
+    /// Whether its initializer has finished
+    defined: bool,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn resolve(
+        statements: &mut [Statement],
+        ast: &'a mut Ast,
+    ) -> Result<(), Vec<CompileError>> {
+        Self::resolve_with_types(statements, ast, &mut HashSet::new())
+    }
+
+    /// Resolves code that can also use the enums in `types`, declared by
+    /// code that ran before it. The ones it declares are added.
+    pub fn resolve_with_types(
+        statements: &mut [Statement],
+        ast: &'a mut Ast,
+        types: &mut HashSet<Symbol>,
+    ) -> Result<(), Vec<CompileError>> {
+        let mut resolver = Self {
+            ast,
+            scopes: Vec::new(),
+            functions: 0,
+            annotations: Vec::new(),
+            enums: Vec::new(),
+            errors: Vec::new(),
+        };
+
+        for stmt in statements {
+            resolver.statement(stmt);
+        }
+
+        types.extend(resolver.enums.drain(..));
+
+        for annotation in std::mem::take(&mut resolver.annotations) {
+            if let Type::Enum(name) = &annotation.ty {
+                if !types.contains(&Symbol::intern(name)) {
+                    resolver.error(annotation.place, &format!("Unknown type '{}'.", name));
+                }
+            }
+        }
+
+        if resolver.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(resolver.errors)
+        }
+    }
+
+    fn scoped(&mut self, names: &[Token], statements: &mut [Statement]) {
+        self.scopes.push(HashMap::new());
+
+        for name in names {
+            self.declare(name);
+            self.define(name);
+        }
+
+        for stmt in statements {
+            self.statement(stmt);
+        }
+
+        self.scopes.pop();
+    }
+
+    fn error(&mut self, place: (usize, usize), msg: &str) {
+        self.errors.push(CompileError::Resolver(place, msg.into()));
+    }
+
+    /// Keeps `annotation` to check that its type exists.
+    fn annotation(&mut self, annotation: &Option<Annotation>) {
+        if let Some(
+            annotation @ Annotation {
+                ty: Type::Enum(_), ..
+            },
+        ) = annotation
+        {
+            self.annotations.push(annotation.clone());
+        }
+    }
+
+    /// Adds `name` to the innermost scope, it can't be read until it is defined.
+    fn declare(&mut self, name: &Token) {
+        let duplicate = match self.scopes.last_mut() {
+            Some(scope) => {
+                let local = Local {
+                    index: scope.len(),
+                    defined: false,
+                };
+                scope.insert(name.lexeme.clone(), local).is_some()
+            }
+            // Globals can be redefined
+            None => false,
+        };
+
+        if duplicate {
+            self.error(
+                name.place,
+                &format!("Already a variable named {} in this scope.", name.lexeme),
+            );
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(local) = self
+            .scopes
+            .last_mut()
+            .and_then(|scope| scope.get_mut(&name.lexeme))
+        {
+            local.defined = true;
+        }
+    }
+
+    /// Where the local `name` lives, `None` if it is a global.
+    fn slot(&self, name: &Token) -> Option<Slot> {
+        self.scopes
+            .iter()
+            .rev()
+            .enumerate()
+            .find_map(|(depth, scope)| {
+                scope.get(&name.lexeme).map(|local| Slot {
+                    depth,
+                    index: local.index,
+                })
+            })
+    }
+
+    fn statement(&mut self, stmt: &mut Statement) {
+        match stmt {
+            Statement::Print(expr) | Statement::Expresion(expr) => self.expression(*expr),
+            Statement::Var(var) => {
+                self.annotation(&var.annotation);
+                self.declare(&var.name);
+
+                if let Some(initializer) = var.initializer {
+                    self.expression(initializer);
+                }
+
+                self.define(&var.name);
+            }
+            Statement::Unpack(names, expr) => {
+                for name in names.iter() {
+                    self.declare(name);
+                }
+
+                self.expression(*expr);
+
+                for name in names.iter() {
+                    self.define(name);
+                }
+            }
+            Statement::Block(statements) => self.scoped(&[], statements),
+            Statement::If(condition, then_branch, else_branch) => {
+                self.expression(*condition);
+                self.statement(then_branch);
+
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            Statement::While(condition, body) => {
+                self.expression(*condition);
+                self.statement(body);
+            }
+            Statement::Function(function) => {
+                // Defined before the body so it can call itself
+                self.declare(&function.name);
+                self.define(&function.name);
+
+                for param in &function.params {
+                    self.annotation(&param.annotation);
+                }
+                self.annotation(&function.returns);
+
+                let params: Vec<_> = function.params.iter().map(|p| p.name.clone()).collect();
+
+                self.functions += 1;
+                self.scoped(&params, &mut Rc::make_mut(function).body);
+                self.functions -= 1;
+            }
+            Statement::Return(keyword, expr) => {
+                if self.functions == 0 {
+                    self.error(keyword.place, "Can't return from top-level code.");
+                }
+
+                self.expression(*expr);
+            }
+            Statement::Enum(decl) => {
+                self.enums.push(decl.name.lexeme.clone());
+                self.declare(&decl.name);
+                self.define(&decl.name);
+            }
+            Statement::Match(_, scrutinee, arms) => {
+                self.expression(*scrutinee);
+
+                for (pattern, arm) in arms {
+                    let bindings = match pattern {
+                        Pattern::Variant(ty, slot, _, bindings) => {
+                            *slot = self.slot(ty);
+                            &bindings[..]
+                        }
+                        Pattern::Wildcard(_) => &[],
+                    };
+
+                    self.scoped(bindings, std::slice::from_mut(arm));
+                }
+            }
+        }
+    }
+
+    fn expression(&mut self, id: ExprId) {
+        match self.ast[id] {
+            Expr::Literal(_) => {}
+            Expr::Binary(lhs, _, rhs) | Expr::Logical(lhs, _, rhs) => {
+                self.expression(lhs);
+                self.expression(rhs);
+            }
+            Expr::Unary(_, expr) | Expr::Grouping(expr) | Expr::Get(expr, _) => {
+                self.expression(expr)
+            }
+            Expr::Variable(name_id, _) => {
+                let name = self.ast[name_id].clone();
+                let initializing = self
+                    .scopes
+                    .last()
+                    .and_then(|scope| scope.get(&name.lexeme))
+                    .is_some_and(|local| !local.defined);
+
+                if initializing {
+                    self.error(
+                        name.place,
+                        "Can't read a local variable in its own initializer.",
+                    );
+                }
+
+                self.ast[id] = Expr::Variable(name_id, self.slot(&name));
+            }
+            Expr::This(keyword) => self.error(
+                self.ast[keyword].place,
+                "Can't use 'this' outside of a class.",
+            ),
+            Expr::Super(keyword, _) => self.error(
+                self.ast[keyword].place,
+                "Can't use 'super' outside of a class.",
+            ),
+            Expr::Assign(name, _, value) => {
+                self.expression(value);
+                let slot = self.slot(&self.ast[name]);
+                self.ast[id] = Expr::Assign(name, slot, value);
+            }
+            Expr::Call(callee, _, args) => {
+                self.expression(callee);
+
+                for i in 0..args.len() {
+                    self.expression(self.ast[args][i]);
+                }
+            }
+            Expr::Tuple(elements) => {
+                for i in 0..elements.len() {
+                    self.expression(self.ast[elements][i]);
+                }
+            }
+        }
+    }
+}
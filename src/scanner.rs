@@ -1,177 +1,205 @@
 use crate::{
     errors::CompileError,
-    token::{Token, TokenType},
+    token::{Lexeme, Token, TokenType},
 };
 
-use std::{str::Chars, str::FromStr};
-
-use peekmore::{PeekMore, PeekMoreIterator};
+use std::str::FromStr;
 
+/// Splits source code into tokens, one at a time as an iterator. It walks the
+/// bytes of the source, places are byte offsets, and the text of every token
+/// is borrowed from it. The last token is always [`TokenType::Eof`].
 pub struct Scanner<'s> {
     pub source_raw: &'s str,
-    source: PeekMoreIterator<Chars<'s>>,
-    tokens: Vec<Token>,
+    source: &'s [u8],
     start: usize,
     current: usize,
+    /// Whether the end of file was already produced
+    done: bool,
 }
 
 impl<'s> Scanner<'s> {
     pub fn new(source_raw: &'s str) -> Self {
         Self {
             source_raw,
-            source: source_raw.chars().peekmore(),
-            tokens: Vec::new(),
+            source: source_raw.as_bytes(),
             start: 0,
             current: 0,
+            done: false,
         }
     }
 
-    pub fn advance(&mut self) -> Option<char> {
+    fn advance(&mut self) -> Option<u8> {
+        let byte = self.peek()?;
         self.current += 1;
 
-        self.source.next()
+        Some(byte)
     }
 
-    pub fn peek(&mut self) -> Option<&char> {
-        self.source.peek()
+    fn peek(&self) -> Option<u8> {
+        self.peek_nth(0)
     }
 
-    fn scan_token(&mut self) -> Result<(), CompileError> {
-        if let Some(c) = self.advance() {
-            let token = match c {
-                '(' => TokenType::LeftParen,
-                ')' => TokenType::RightParen,
-
-                '{' => TokenType::LeftBrace,
-                '}' => TokenType::RightBrace,
-
-                '.' => TokenType::Dot,
-                ',' => TokenType::Comma,
-                ';' => TokenType::Semicolon,
-
-                '-' => TokenType::Minus,
-                '+' => TokenType::Plus,
-                '*' => TokenType::Star,
-                '/' => {
-                    if self.peek() == Some(&'/') {
-                        // it is a comment, skip until end of line
-                        while self.peek().is_some() && self.peek() != Some(&'\n') {
-                            self.advance();
-                        }
-
-                        return Ok(());
-                    }
-                    // TODO comment blocks: /* ... */
-                    TokenType::Slash
-                }
-
-                // One or two character tokens
-                // TODO repetitive code
-                '!' => {
-                    if self.source.peek() == Some(&'=') {
-                        self.advance();
-                        TokenType::BangEqual
-                    } else {
-                        TokenType::Bang
-                    }
-                }
-
-                '=' => {
-                    if self.source.peek() == Some(&'=') {
-                        self.advance();
-                        TokenType::EqualEqual
-                    } else {
-                        TokenType::Equal
-                    }
-                }
-
-                '<' => {
-                    if self.source.peek() == Some(&'=') {
-                        self.advance();
-                        TokenType::LessEqual
-                    } else {
-                        TokenType::Less
-                    }
-                }
+    fn peek_nth(&self, n: usize) -> Option<u8> {
+        self.source.get(self.current + n).copied()
+    }
 
-                '>' => {
-                    if self.source.peek() == Some(&'=') {
-                        self.advance();
-                        TokenType::GreaterEqual
-                    } else {
-                        TokenType::Greater
-                    }
-                }
+    /// Consumes the next byte if it is `expected`.
+    fn matches(&mut self, expected: u8) -> bool {
+        let matches = self.peek() == Some(expected);
 
-                // Skip whitespace
-                ' ' | '\r' | '\t' => {
-                    return Ok(());
-                }
+        if matches {
+            self.current += 1;
+        }
 
-                '\n' => {
-                    return Ok(());
-                }
+        matches
+    }
 
-                // Strings
-                '"' => {
-                    return self.string();
-                }
+    /// The character starting at `current`, for what is not ASCII.
+    fn char(&self) -> Option<char> {
+        self.source_raw[self.current..].chars().next()
+    }
 
-                // Numbers
-                '0'..='9' => {
-                    return self.number();
-                }
+    /// The next token, `None` for what is skipped, like whitespace.
+    fn scan_token(&mut self) -> Result<Option<Lexeme<'s>>, CompileError> {
+        let byte = match self.advance() {
+            Some(byte) => byte,
+            None => return Ok(None),
+        };
 
-                _ => {
-                    if c.is_alphabetic() {
-                        self.identifier();
-                        return Ok(());
-                    } else {
-                        return Err(CompileError::Scanner(
-                            (self.start, self.current),
-                            format!("Unexpected char: {}", c),
-                        ));
-                    }
+        let token = match byte {
+            b'(' => TokenType::LeftParen,
+            b')' => TokenType::RightParen,
+
+            b'{' => TokenType::LeftBrace,
+            b'}' => TokenType::RightBrace,
+
+            b'.' => TokenType::Dot,
+            b',' => TokenType::Comma,
+            b';' => TokenType::Semicolon,
+            b':' => TokenType::Colon,
+
+            b'-' if self.matches(b'>') => TokenType::Arrow,
+            b'-' => TokenType::Minus,
+            b'+' => TokenType::Plus,
+            b'*' => TokenType::Star,
+            b'%' => TokenType::Percent,
+            b'/' if self.peek() == Some(b'/') => return Ok(self.line_comment()),
+            b'/' if self.peek() == Some(b'*') => return self.block_comment(),
+            b'/' => TokenType::Slash,
+
+            // One or two character tokens
+            b'!' if self.matches(b'=') => TokenType::BangEqual,
+            b'!' => TokenType::Bang,
+            b'=' if self.matches(b'=') => TokenType::EqualEqual,
+            b'=' if self.matches(b'>') => TokenType::FatArrow,
+            b'=' => TokenType::Equal,
+            b'<' if self.matches(b'=') => TokenType::LessEqual,
+            b'<' => TokenType::Less,
+            b'>' if self.matches(b'=') => TokenType::GreaterEqual,
+            b'>' => TokenType::Greater,
+
+            // Skip whitespace
+            b' ' | b'\r' | b'\t' | b'\n' => return Ok(None),
+
+            b'"' => return self.string().map(Some),
+            b'0'..=b'9' => return self.number().map(Some),
+            b'a'..=b'z' | b'A'..=b'Z' => return Ok(Some(self.identifier())),
+
+            _ => {
+                // Letters can take more than a byte
+                self.current = self.start;
+                let c = self.char().unwrap_or(char::REPLACEMENT_CHARACTER);
+                self.current += c.len_utf8();
+
+                if c.is_alphabetic() {
+                    return Ok(Some(self.identifier()));
                 }
-            };
 
-            self.add_token(token);
-        }
+                return Err(CompileError::Scanner(
+                    (self.start, self.current),
+                    format!("Unexpected char: {}", c),
+                ));
+            }
+        };
 
-        Ok(())
+        Ok(Some(self.lexeme(token)))
     }
 
+    /// Every token up to the end of file, or every error in the source.
     pub fn scan_tokens(&mut self) -> Result<Vec<Token>, Vec<CompileError>> {
+        let mut tokens = Vec::new();
         let mut errors = Vec::new();
 
-        while self.peek().is_some() {
-            self.start = self.current;
-            if let Err(e) = self.scan_token() {
-                errors.push(e);
+        for lexeme in self.by_ref() {
+            match lexeme {
+                Ok(lexeme) => tokens.push(lexeme.into()),
+                Err(e) => errors.push(e),
             }
         }
 
-        self.tokens.push(Token::new(
-            TokenType::Eof,
-            String::new(),
-            (self.start, self.current),
-        ));
-
         if errors.is_empty() {
-            Ok(self.tokens.clone())
+            Ok(tokens)
         } else {
             Err(errors)
         }
     }
 
-    fn add_token(&mut self, token: TokenType) {
-        let text = self.source_raw[self.start..self.current].into();
-        self.tokens
-            .push(Token::new(token, text, (self.start, self.current)));
+    /// A token spanning from `start` to `current`.
+    fn lexeme(&self, token: TokenType) -> Lexeme<'s> {
+        Lexeme {
+            _type: token,
+            text: &self.source_raw[self.start..self.current],
+            place: (self.start, self.current),
+        }
+    }
+
+    /// `// ...` is skipped, `/// ...` is a doc comment but `//// ...` is not.
+    fn line_comment(&mut self) -> Option<Lexeme<'s>> {
+        self.advance();
+
+        let is_doc = self.peek() == Some(b'/') && self.peek_nth(1) != Some(b'/');
+
+        while self.peek().is_some_and(|byte| byte != b'\n') {
+            self.advance();
+        }
+
+        if !is_doc {
+            return None;
+        }
+
+        let text = &self.source_raw[self.start + 3..self.current];
+
+        Some(Lexeme {
+            text: text.strip_prefix(' ').unwrap_or(text).trim_end(),
+            ..self.lexeme(TokenType::DocComment)
+        })
+    }
+
+    /// `/* ... */`, they can be nested.
+    fn block_comment(&mut self) -> Result<Option<Lexeme<'s>>, CompileError> {
+        self.advance();
+        let mut depth = 1;
+
+        while depth > 0 {
+            match self.advance() {
+                Some(b'/') if self.matches(b'*') => depth += 1,
+                Some(b'*') if self.matches(b'/') => depth -= 1,
+                Some(_) => {}
+                None => {
+                    return Err(CompileError::Scanner(
+                        (self.start, self.start + 2),
+                        "Unterminated comment".into(),
+                    ))
+                }
+            }
+        }
+
+        Ok(None)
     }
 
-    fn string(&mut self) -> Result<(), CompileError> {
-        while self.peek().is_some() && self.peek() != Some(&'"') {
+    fn string(&mut self) -> Result<Lexeme<'s>, CompileError> {
+        // A quote is never part of a longer UTF-8 character
+        while self.peek().is_some_and(|byte| byte != b'"') {
             self.advance();
         }
 
@@ -185,40 +213,25 @@ impl<'s> Scanner<'s> {
         // The closing "
         self.advance();
 
-        let value = self.source_raw[self.start + 1..self.current - 1].into();
-        self.add_token(TokenType::String(value));
-
-        Ok(())
+        Ok(self.lexeme(TokenType::String))
     }
 
-    fn number(&mut self) -> Result<(), CompileError> {
-        // TODO repetitive code
-        while let Some(c) = self.peek() {
-            if c.is_numeric() {
-                self.advance();
-            } else {
-                break;
-            }
+    fn digits(&mut self) {
+        while self.peek().is_some_and(|byte| byte.is_ascii_digit()) {
+            self.advance();
         }
+    }
+
+    fn number(&mut self) -> Result<Lexeme<'s>, CompileError> {
+        self.digits();
 
-        // TODO repetitive code                                             v HACK
-        if self.peek() == Some(&'.') && self.source.peek_nth(1).unwrap_or(&'*').is_numeric() {
+        if self.peek() == Some(b'.') && self.peek_nth(1).is_some_and(|b| b.is_ascii_digit()) {
             self.advance();
-            while let Some(c) = self.peek() {
-                if c.is_numeric() {
-                    self.advance();
-                } else {
-                    break;
-                }
-            }
+            self.digits();
         }
 
         match f64::from_str(&self.source_raw[self.start..self.current]) {
-            Ok(n) => {
-                self.add_token(TokenType::Number(n));
-
-                Ok(())
-            }
+            Ok(n) => Ok(self.lexeme(TokenType::Number(n))),
             Err(_) => Err(CompileError::Scanner(
                 (self.start, self.current),
                 "Not a number".into(),
@@ -226,12 +239,15 @@ impl<'s> Scanner<'s> {
         }
     }
 
-    fn identifier(&mut self) {
-        while let Some(c) = self.peek() {
-            if c.is_alphanumeric() {
-                self.advance();
-            } else {
-                break;
+    fn identifier(&mut self) -> Lexeme<'s> {
+        loop {
+            match self.peek() {
+                Some(byte) if byte.is_ascii_alphanumeric() => self.current += 1,
+                Some(byte) if !byte.is_ascii() => match self.char() {
+                    Some(c) if c.is_alphanumeric() => self.current += c.len_utf8(),
+                    _ => break,
+                },
+                _ => break,
             }
         }
 
@@ -252,9 +268,38 @@ impl<'s> Scanner<'s> {
             "this" => TokenType::This,
             "print" => TokenType::Print,
             "return" => TokenType::Return,
+            "enum" => TokenType::Enum,
+            "match" => TokenType::Match,
             _ => TokenType::Identifier,
         };
 
-        self.add_token(token);
+        self.lexeme(token)
+    }
+}
+
+/// Tokens are only scanned when they are asked for, errors don't stop it.
+impl<'s> Iterator for Scanner<'s> {
+    type Item = Result<Lexeme<'s>, CompileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.current < self.source.len() {
+            self.start = self.current;
+
+            match self.scan_token() {
+                Ok(Some(lexeme)) => return Some(Ok(lexeme)),
+                Ok(None) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        if self.done {
+            return None;
+        }
+
+        self.done = true;
+        Some(Ok(Lexeme {
+            text: "",
+            ..self.lexeme(TokenType::Eof)
+        }))
     }
 }
@@ -1,14 +1,92 @@
-use crate::{expr::Expr, token::Token};
+use crate::{
+    expr::{Ast, ExprId, Slot},
+    token::Token,
+    types::Annotation,
+};
+
+use std::rc::Rc;
 
 // TODO use named fields
+/// Expressions are ids in the [`Ast`](crate::expr::Ast) the statements were
+/// parsed into.
 #[derive(Clone, Debug)]
 pub enum Statement {
-    Print(Expr),
-    Expresion(Expr),
-    Var(Token, Expr),
+    Print(ExprId),
+    Expresion(ExprId),
+    Var(VarDecl),
+    /// `var a, b = tuple;`
+    Unpack(Vec<Token>, ExprId),
     Block(Vec<Statement>),
-    If(Expr, Box<Statement>, Option<Box<Statement>>),
-    While(Expr, Box<Statement>),
-    Function(Token, Vec<Token>, Vec<Statement>),
-    Return(Token, Expr),
+    If(ExprId, Box<Statement>, Option<Box<Statement>>),
+    While(ExprId, Box<Statement>),
+    /// Shared with the functions created out of it. Until the script runs
+    /// only the tree holds it, so the passes before that can change it with
+    /// `Rc::make_mut` without copying it.
+    Function(Rc<FunctionDecl>),
+    Return(Token, ExprId),
+    Enum(EnumDecl),
+    /// The `match` keyword, the scrutinee and the arms in source order
+    Match(Token, ExprId, Vec<(Pattern, Statement)>),
+}
+
+impl Statement {
+    /// Where the statement starts, to point at it. Empty blocks have nothing
+    /// to point at.
+    pub fn place(&self, ast: &Ast) -> Option<(usize, usize)> {
+        match self {
+            Statement::Print(expr) | Statement::Expresion(expr) => Some(ast.place(*expr)),
+            Statement::Var(var) => Some(var.name.place),
+            Statement::Unpack(names, _) => Some(names[0].place),
+            Statement::Block(statements) => statements.first().and_then(|stmt| stmt.place(ast)),
+            Statement::If(condition, ..) | Statement::While(condition, _) => {
+                Some(ast.place(*condition))
+            }
+            Statement::Function(function) => Some(function.name.place),
+            Statement::Return(keyword, _) | Statement::Match(keyword, ..) => Some(keyword.place),
+            Statement::Enum(decl) => Some(decl.name.place),
+        }
+    }
+}
+
+/// `var name: type = initializer;`
+#[derive(Clone, Debug)]
+pub struct VarDecl {
+    /// The text of the `///` comments right before the declaration
+    pub doc: Option<String>,
+    pub name: Token,
+    pub annotation: Option<Annotation>,
+    pub initializer: Option<ExprId>,
+}
+
+/// `fn name(param: type, ...) -> type { body }`
+#[derive(Clone, Debug)]
+pub struct FunctionDecl {
+    pub doc: Option<String>,
+    pub name: Token,
+    pub params: Vec<Param>,
+    pub returns: Option<Annotation>,
+    pub body: Vec<Statement>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Param {
+    pub name: Token,
+    pub annotation: Option<Annotation>,
+}
+
+/// `enum Name { Variant(field, ...), ... }`
+#[derive(Clone, Debug)]
+pub struct EnumDecl {
+    pub doc: Option<String>,
+    pub name: Token,
+    /// Every variant with the names of its payload fields
+    pub variants: Vec<(Token, Vec<Token>)>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    /// `Enum.Variant(binding, ...)`, the enum is found like a variable
+    Variant(Token, Option<Slot>, Token, Vec<Token>),
+    /// `else`, matches anything
+    Wildcard(Token),
 }
@@ -0,0 +1,118 @@
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    fmt::{Debug, Display, Formatter},
+    hash::{Hash, Hasher},
+    ops::Deref,
+    rc::Rc,
+};
+
+thread_local! {
+    /// The symbols of the scripts running on this thread.
+    static SYMBOLS: RefCell<Interner> = RefCell::new(Interner::default());
+}
+
+/// Each text is stored once. Texts that no symbol refers to anymore are
+/// dropped when the table has doubled since it was last cleaned, so it only
+/// holds about twice the names in use.
+#[derive(Default)]
+struct Interner {
+    texts: HashSet<Rc<str>>,
+    /// How many texts it can hold before it is cleaned again
+    clean_at: usize,
+}
+
+impl Interner {
+    fn intern(&mut self, text: &str) -> Rc<str> {
+        if let Some(text) = self.texts.get(text) {
+            return text.clone();
+        }
+
+        if self.texts.len() >= self.clean_at {
+            self.texts.retain(|text| Rc::strong_count(text) > 1);
+            self.clean_at = (self.texts.len() * 2).max(1024);
+        }
+
+        let text: Rc<str> = text.into();
+        self.texts.insert(text.clone());
+        text
+    }
+}
+
+/// An interned name from source code, like the name of a variable. Equal
+/// texts are the same symbol while one of them is alive, so comparing and
+/// hashing one doesn't look at its characters.
+///
+/// Symbols are interned per thread, one can't be sent to another thread.
+///
+/// ```
+/// use lux::symbol::Symbol;
+///
+/// let name = Symbol::intern("count");
+/// assert_eq!(name, Symbol::intern(&String::from("count")));
+/// assert_eq!(name.as_str(), "count");
+/// ```
+#[derive(Clone)]
+pub struct Symbol(Rc<str>);
+
+impl Symbol {
+    pub fn intern(text: &str) -> Self {
+        Symbol(SYMBOLS.with(|symbols| symbols.borrow_mut().intern(text)))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Rc::as_ptr(&self.0).cast::<u8>().hash(state)
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(text: &str) -> Self {
+        Symbol::intern(text)
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&*self.0, f)
+    }
+}
+
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&*self.0, f)
+    }
+}
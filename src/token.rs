@@ -1,4 +1,6 @@
-#[derive(Debug, Clone, PartialEq)]
+use crate::symbol::Symbol;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TokenType {
     /// Single character tokens
     LeftParen,
@@ -10,8 +12,10 @@ pub enum TokenType {
     Minus,
     Plus,
     Semicolon,
+    Colon,
     Slash,
     Star,
+    Percent,
 
     /// One or two character tokens
     Bang,
@@ -22,11 +26,16 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    FatArrow,
+    Arrow,
 
     /// Literals
     Identifier,
-    String(String),
+    /// The value is its text in the source without the quotes
+    String,
     Number(f64),
+    /// A `///` comment, the lexeme is its text
+    DocComment,
 
     /// Keywords
     If,
@@ -45,6 +54,8 @@ pub enum TokenType {
     This,
     Print,
     Return,
+    Enum,
+    Match,
 
     Eof,
 }
@@ -52,16 +63,39 @@ pub enum TokenType {
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub _type: TokenType,
-    pub lexeme: String,
+    /// The text of names, keywords and operators. It is empty for string and
+    /// number literals, their text is only read from the source.
+    pub lexeme: Symbol,
     pub place: (usize, usize),
 }
 
 impl Token {
-    pub fn new(_type: TokenType, lexeme: String, place: (usize, usize)) -> Self {
+    pub fn new(_type: TokenType, lexeme: &str, place: (usize, usize)) -> Self {
         Self {
             _type,
-            lexeme,
+            lexeme: Symbol::intern(lexeme),
             place,
         }
     }
 }
+
+/// A token as the [`Scanner`](crate::scanner::Scanner) finds it, its text is
+/// borrowed from the source until it becomes a [`Token`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lexeme<'s> {
+    pub _type: TokenType,
+    pub text: &'s str,
+    pub place: (usize, usize),
+}
+
+impl From<Lexeme<'_>> for Token {
+    fn from(lexeme: Lexeme<'_>) -> Self {
+        let text = match lexeme._type {
+            // Any text can be a literal, only names are worth interning
+            TokenType::String | TokenType::Number(_) => "",
+            _ => lexeme.text,
+        };
+
+        Token::new(lexeme._type, text, lexeme.place)
+    }
+}
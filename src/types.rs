@@ -0,0 +1,72 @@
+use crate::expr::Value;
+
+use std::fmt::{Display, Formatter};
+
+/// The types that can be written in an annotation: `var name: string = ...`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Any,
+    Number,
+    String,
+    Bool,
+    Nil,
+    Function,
+    Tuple,
+    /// Values of the enum with the given name
+    Enum(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub ty: Type,
+    pub place: (usize, usize),
+}
+
+impl Type {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "any" => Type::Any,
+            "number" => Type::Number,
+            "string" => Type::String,
+            "bool" => Type::Bool,
+            "nil" => Type::Nil,
+            "fn" => Type::Function,
+            "tuple" => Type::Tuple,
+            other => Type::Enum(other.into()),
+        }
+    }
+
+    pub fn of(value: &Value) -> Self {
+        match value {
+            Value::String(_) => Type::String,
+            Value::Number(_) => Type::Number,
+            Value::Boolean(_) => Type::Bool,
+            Value::Callable(_) => Type::Function,
+            // There are no annotations for enum types themselves
+            Value::Enum(_) => Type::Any,
+            Value::Tagged(t) => Type::Enum(t.ty.name.clone()),
+            Value::Tuple(_) => Type::Tuple,
+            Value::Nil => Type::Nil,
+        }
+    }
+
+    /// Whether a value of type `other` can be used where `self` is expected.
+    pub fn accepts(&self, other: &Type) -> bool {
+        *self == Type::Any || *other == Type::Any || self == other
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Any => write!(f, "any"),
+            Type::Number => write!(f, "number"),
+            Type::String => write!(f, "string"),
+            Type::Bool => write!(f, "bool"),
+            Type::Nil => write!(f, "nil"),
+            Type::Function => write!(f, "fn"),
+            Type::Tuple => write!(f, "tuple"),
+            Type::Enum(name) => write!(f, "{}", name),
+        }
+    }
+}
@@ -0,0 +1,676 @@
+use crate::{
+    budget::Budget,
+    chunk::{Op, Proto},
+    compiler::{Compiler, Globals},
+    errors::CompileError,
+    expr::{Ast, Enum, Function, NumericPolicy, Tagged, Value},
+    gc::{self, Heap, OutOfMemory, Stats},
+    interpreter::{check_type, overflow, DEFAULT_MAX_DEPTH},
+    natives::{Capabilities, Context, NATIVES},
+    statements::Statement,
+};
+
+use std::{cell::RefCell, cmp::Ordering, collections::HashSet, iter, mem, rc::Rc, time::Duration};
+
+/// A function value created by the virtual machine.
+#[derive(Debug)]
+pub struct Closure {
+    pub proto: Rc<Proto>,
+    pub upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+/// A variable captured by a closure. It stays in the stack while its scope
+/// runs and moves to the upvalue when the scope ends.
+#[derive(Debug)]
+pub enum Upvalue {
+    Open(usize),
+    Closed(Value),
+}
+
+struct Frame {
+    closure: Rc<Closure>,
+    ip: usize,
+    /// Stack index of slot 0
+    base: usize,
+    /// The functions whose return annotation applies to the value returned
+    /// by the frame, with the call it comes from. Tail calls reuse the frame
+    /// and add their own the first time.
+    returns: Vec<(Rc<Proto>, (usize, usize))>,
+    /// The functions in `returns`
+    annotated: HashSet<*const Proto>,
+}
+
+/// Stack based virtual machine, an alternative to the tree walking
+/// [`Interpreter`](crate::interpreter::Interpreter) that runs the bytecode
+/// of the [`Compiler`]. Both produce the same output and errors.
+pub struct Vm {
+    names: Globals,
+    globals: Vec<Option<Value>>,
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    /// Upvalues that still point into the stack
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    type_checks: bool,
+    numeric: NumericPolicy,
+    max_depth: usize,
+    heap: Heap,
+    budget: Budget,
+    capabilities: Capabilities,
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        let mut vm = Self {
+            names: Globals::default(),
+            globals: Vec::new(),
+            stack: Vec::new(),
+            frames: Vec::new(),
+            open_upvalues: Vec::new(),
+            type_checks: false,
+            numeric: NumericPolicy::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            heap: Heap::default(),
+            budget: Budget::default(),
+            capabilities: Capabilities::default(),
+        };
+
+        for (name, arity, body) in NATIVES {
+            let slot = vm.names.slot(name) as usize;
+            vm.globals.resize(slot + 1, None);
+            vm.globals[slot] = Some(Value::Callable(Rc::new(Function::Native {
+                name,
+                arity,
+                body: Box::new(body),
+            })));
+        }
+
+        vm
+    }
+}
+
+impl Vm {
+    pub fn with_type_checks(mut self, enabled: bool) -> Self {
+        self.type_checks = enabled;
+        self
+    }
+
+    pub fn with_numeric_policy(mut self, policy: NumericPolicy) -> Self {
+        self.numeric = policy;
+        self
+    }
+
+    /// Calls nested deeper than `depth` are a "Stack overflow" error, the
+    /// frames live on the heap so it doesn't need a large host stack.
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Running out of the budget is a [`CompileError::OutOfMemory`].
+    pub fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.heap.set_limit(bytes);
+        self
+    }
+
+    /// Every instruction is a step, taking more than `steps` in a call to
+    /// [`execute`](Self::execute) interrupts it.
+    pub fn with_max_steps(mut self, steps: u64) -> Self {
+        self.budget.set_steps(steps);
+        self
+    }
+
+    /// Calls to [`execute`](Self::execute) running for longer than
+    /// `timeout` are interrupted.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.budget.set_timeout(timeout);
+        self
+    }
+
+    /// Replaces what the natives are allowed to do, reading the clock by
+    /// default.
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// What the cycle collector has done so far.
+    pub fn gc_stats(&self) -> Stats {
+        self.heap.stats()
+    }
+
+    pub fn interpret(&mut self, statements: &[Statement], ast: &Ast) -> Result<(), CompileError> {
+        let script = self.compile(statements, ast);
+        self.execute(script)
+    }
+
+    /// Compiles a script with the globals of this machine.
+    pub fn compile(&mut self, statements: &[Statement], ast: &Ast) -> Proto {
+        Compiler::compile(statements, ast, &mut self.names)
+    }
+
+    /// The name of every global slot known to the machine.
+    pub fn globals(&self) -> &[String] {
+        &self.names.names
+    }
+
+    /// Gets the machine ready to run a script compiled by another machine
+    /// with the given globals.
+    pub fn link(&mut self, globals: &[String]) -> Result<(), String> {
+        for (slot, name) in globals.iter().enumerate() {
+            if self.names.slot(name) as usize != slot {
+                return Err(format!("The global {} is in a different slot.", name));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn execute(&mut self, script: Proto) -> Result<(), CompileError> {
+        self.globals.resize(self.names.names.len(), None);
+        self.budget.start();
+
+        let closure = Rc::new(Closure {
+            proto: Rc::new(script),
+            upvalues: Vec::new(),
+        });
+
+        self.stack
+            .push(Value::Callable(Rc::new(Function::Closure(closure.clone()))));
+        self.frames.push(Frame {
+            closure,
+            ip: 0,
+            base: 0,
+            returns: Vec::new(),
+            annotated: HashSet::new(),
+        });
+
+        let result = self.run();
+
+        if result.is_err() {
+            self.stack.clear();
+            self.frames.clear();
+            self.open_upvalues.clear();
+        }
+
+        result
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("the compiler balances the stack")
+    }
+
+    fn peek(&self) -> &Value {
+        self.stack.last().expect("the compiler balances the stack")
+    }
+
+    fn run(&mut self) -> Result<(), CompileError> {
+        let mut frame = self.frames.pop().expect("a frame to run");
+
+        loop {
+            let op = frame.closure.proto.chunk.code[frame.ip];
+            frame.ip += 1;
+
+            // The span of the instruction that is running
+            let at = |frame: &Frame, msg: String| {
+                CompileError::Interpreter(frame.closure.proto.chunk.spans[frame.ip - 1], msg)
+            };
+            let out_of_memory = |frame: &Frame, e: OutOfMemory| {
+                CompileError::OutOfMemory(frame.closure.proto.chunk.spans[frame.ip - 1], e)
+            };
+
+            self.budget.step().map_err(|interrupt| {
+                CompileError::Interrupted(frame.closure.proto.chunk.spans[frame.ip - 1], interrupt)
+            })?;
+
+            match op {
+                Op::Constant(index) => {
+                    let value = frame.closure.proto.chunk.constants[index as usize].clone();
+                    self.stack.push(value);
+                }
+                Op::Nil => self.stack.push(Value::Nil),
+                Op::Pop => {
+                    self.pop();
+                }
+                Op::GetLocal(slot) => {
+                    let value = self.stack[frame.base + slot as usize].clone();
+                    self.stack.push(value);
+                }
+                Op::SetLocal(slot) => {
+                    let value = self.peek().clone();
+                    self.stack[frame.base + slot as usize] = value;
+                }
+                Op::GetUpvalue(index) => {
+                    let value = match &*frame.closure.upvalues[index as usize].borrow() {
+                        Upvalue::Open(slot) => self.stack[*slot].clone(),
+                        Upvalue::Closed(value) => value.clone(),
+                    };
+                    self.stack.push(value);
+                }
+                Op::SetUpvalue(index) => {
+                    let value = self.peek().clone();
+
+                    match &mut *frame.closure.upvalues[index as usize].borrow_mut() {
+                        Upvalue::Open(slot) => self.stack[*slot] = value,
+                        Upvalue::Closed(closed) => *closed = value,
+                    }
+                }
+                Op::GetGlobal(slot) => match &self.globals[slot as usize] {
+                    Some(value) => self.stack.push(value.clone()),
+                    None => {
+                        let name = &self.names.names[slot as usize];
+                        return Err(at(
+                            &frame,
+                            format!("Undefined variable or function: {}.", name),
+                        ));
+                    }
+                },
+                Op::SetGlobal(slot) => {
+                    if self.globals[slot as usize].is_none() {
+                        let name = &self.names.names[slot as usize];
+                        return Err(at(&frame, format!("Undefined variable: {}.", name)));
+                    }
+
+                    self.globals[slot as usize] = Some(self.peek().clone());
+                }
+                Op::DefineGlobal(slot) => {
+                    let value = self.pop();
+                    self.globals[slot as usize] = Some(value);
+                }
+                Op::Negate => {
+                    let value = self.pop().neg(self.numeric).map_err(|e| at(&frame, e))?;
+                    self.stack.push(value);
+                }
+                Op::Not => {
+                    let value = !self.pop().is_truthy();
+                    self.stack.push(Value::Boolean(value));
+                }
+                Op::Add
+                | Op::Subtract
+                | Op::Multiply
+                | Op::Divide
+                | Op::Remainder
+                | Op::Greater
+                | Op::GreaterEqual
+                | Op::Less
+                | Op::LessEqual => {
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+
+                    let value = match op {
+                        Op::Add => lhs.add(rhs, self.numeric),
+                        Op::Subtract => lhs.sub(rhs, self.numeric),
+                        Op::Multiply => lhs.mul(rhs, self.numeric),
+                        Op::Divide => lhs.div(rhs, self.numeric),
+                        Op::Remainder => lhs.rem(rhs, self.numeric),
+                        _ => lhs.compare(&rhs).map(|ordering| {
+                            let test = match op {
+                                Op::Greater => Ordering::is_gt,
+                                Op::GreaterEqual => Ordering::is_ge,
+                                Op::Less => Ordering::is_lt,
+                                _ => Ordering::is_le,
+                            };
+
+                            Value::Boolean(ordering.is_some_and(test))
+                        }),
+                    };
+
+                    let value = value.map_err(|e| at(&frame, e))?;
+                    self.heap
+                        .allocate(
+                            gc::size(&value),
+                            self.stack.iter().chain(self.globals.iter().flatten()),
+                        )
+                        .map_err(|e| out_of_memory(&frame, e))?;
+                    self.stack.push(value);
+                }
+                Op::Equal | Op::NotEqual => {
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+                    self.stack
+                        .push(Value::Boolean((lhs == rhs) == (op == Op::Equal)));
+                }
+                Op::Jump(target) => frame.ip = target as usize,
+                Op::JumpIfFalse(target) => {
+                    if !self.peek().is_truthy() {
+                        frame.ip = target as usize;
+                    }
+                }
+                Op::JumpIfTrue(target) => {
+                    if self.peek().is_truthy() {
+                        frame.ip = target as usize;
+                    }
+                }
+                Op::Call(site) | Op::TailCall(site) => {
+                    let args = frame.closure.proto.chunk.calls[site as usize].args.len();
+                    let base = self.stack.len() - args - 1;
+
+                    let callee = match &self.stack[base] {
+                        Value::Callable(f) => f.clone(),
+                        _ => return Err(at(&frame, "Not a callable object.".into())),
+                    };
+
+                    let arity = match &*callee {
+                        Function::Native { arity, .. } => *arity,
+                        Function::Closure(closure) => closure.proto.params.len(),
+                        Function::Constructor { ty, variant } => ty.variants[*variant].1,
+                        Function::User { declaration, .. } => declaration.params.len(),
+                    };
+
+                    if arity != args {
+                        return Err(at(
+                            &frame,
+                            format!("Expected {} arguments but got {}.", arity, args),
+                        ));
+                    }
+
+                    match &*callee {
+                        Function::Closure(closure) => {
+                            let closure = closure.clone();
+                            let site = &frame.closure.proto.chunk.calls[site as usize];
+
+                            if self.type_checks {
+                                for (i, param) in closure.proto.params.iter().enumerate() {
+                                    check_type(param, &self.stack[base + 1 + i], site.args[i])?;
+                                }
+                            }
+
+                            let tail = matches!(op, Op::TailCall(_));
+
+                            // The script is the first frame, it isn't a call
+                            if !tail && self.frames.len() + 1 > self.max_depth {
+                                let running = Some(&frame).filter(|_| !self.frames.is_empty());
+                                let names = iter::once(&closure)
+                                    .chain(running.map(|frame| &frame.closure))
+                                    .chain(self.frames.iter().skip(1).rev().map(|f| &f.closure))
+                                    .map(|closure| closure.proto.name.as_str());
+
+                                return Err(overflow(
+                                    frame.closure.proto.chunk.spans[frame.ip - 1],
+                                    self.max_depth,
+                                    names,
+                                ));
+                            }
+
+                            self.heap
+                                .allocate(
+                                    gc::FRAME,
+                                    self.stack.iter().chain(self.globals.iter().flatten()),
+                                )
+                                .map_err(|e| out_of_memory(&frame, e))?;
+
+                            let (mut returns, mut annotated) = if tail {
+                                (
+                                    mem::take(&mut frame.returns),
+                                    mem::take(&mut frame.annotated),
+                                )
+                            } else {
+                                Default::default()
+                            };
+
+                            // The same annotation checks the same value, however
+                            // many times the function was tail called
+                            if self.type_checks
+                                && closure.proto.returns.is_some()
+                                && annotated.insert(Rc::as_ptr(&closure.proto))
+                            {
+                                returns.push((closure.proto.clone(), site.place));
+                            }
+
+                            if tail {
+                                // The callee and its arguments take the place of the caller
+                                self.close_upvalues(frame.base);
+                                self.stack.drain(frame.base..base);
+
+                                frame = Frame {
+                                    closure,
+                                    ip: 0,
+                                    base: frame.base,
+                                    returns,
+                                    annotated,
+                                };
+                            } else {
+                                let call = Frame {
+                                    closure,
+                                    ip: 0,
+                                    base,
+                                    returns,
+                                    annotated,
+                                };
+                                self.frames.push(mem::replace(&mut frame, call));
+                            }
+                        }
+                        Function::Native { body, .. } => {
+                            let mut cx = Context {
+                                heap: &mut self.heap,
+                                capabilities: &self.capabilities,
+                            };
+                            let value = body(&mut cx, &self.stack[base + 1..])
+                                .map_err(|e| at(&frame, e))?;
+                            self.stack.truncate(base);
+                            self.heap
+                                .allocate(
+                                    gc::size(&value),
+                                    self.stack.iter().chain(self.globals.iter().flatten()),
+                                )
+                                .map_err(|e| out_of_memory(&frame, e))?;
+                            self.stack.push(value);
+                        }
+                        Function::Constructor { ty, variant } => {
+                            let payload = self.stack.split_off(base + 1);
+                            self.stack.truncate(base);
+
+                            let value = Value::Tagged(Rc::new(Tagged {
+                                ty: ty.clone(),
+                                variant: *variant,
+                                payload,
+                            }));
+                            self.heap
+                                .allocate(
+                                    gc::size(&value),
+                                    self.stack.iter().chain(self.globals.iter().flatten()),
+                                )
+                                .map_err(|e| out_of_memory(&frame, e))?;
+                            self.stack.push(value);
+                        }
+                        Function::User { .. } => {
+                            unreachable!("only the tree walking interpreter creates them")
+                        }
+                    }
+                }
+                Op::Closure(index) => {
+                    let proto = frame.closure.proto.chunk.functions[index as usize].clone();
+
+                    let upvalues = proto
+                        .upvalues
+                        .iter()
+                        .map(|(local, index)| {
+                            if *local {
+                                self.capture(frame.base + *index as usize)
+                            } else {
+                                frame.closure.upvalues[*index as usize].clone()
+                            }
+                        })
+                        .collect();
+
+                    let closure = Rc::new(Closure { proto, upvalues });
+                    self.stack
+                        .push(Value::Callable(Rc::new(Function::Closure(closure))));
+                }
+                Op::CloseUpvalue => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.pop();
+                }
+                Op::Return => {
+                    let value = self.pop();
+                    self.close_upvalues(frame.base);
+                    self.stack.truncate(frame.base);
+
+                    // Innermost first, like nested calls would
+                    for (proto, place) in frame.returns.iter().rev() {
+                        check_type(&proto.returns, &value, *place)?;
+                    }
+
+                    match self.frames.pop() {
+                        Some(caller) => {
+                            frame = caller;
+                            self.stack.push(value);
+                        }
+                        None => return Ok(()),
+                    }
+                }
+                Op::Print => println!("{}", self.pop()),
+                Op::Tuple(len) => {
+                    let values = self.stack.split_off(self.stack.len() - len as usize);
+                    let value = Value::Tuple(values.into());
+                    self.heap
+                        .allocate(
+                            gc::size(&value),
+                            self.stack.iter().chain(self.globals.iter().flatten()),
+                        )
+                        .map_err(|e| out_of_memory(&frame, e))?;
+                    self.stack.push(value);
+                }
+                Op::Unpack(len) => {
+                    let values = match self.pop() {
+                        Value::Tuple(values) => values,
+                        other => {
+                            return Err(at(
+                                &frame,
+                                format!("Can only unpack tuples, got {}.", other),
+                            ))
+                        }
+                    };
+
+                    if values.len() != len as usize {
+                        return Err(at(
+                            &frame,
+                            format!(
+                                "Expected {} values to unpack but got {}.",
+                                len,
+                                values.len()
+                            ),
+                        ));
+                    }
+
+                    self.stack.extend(values.iter().cloned());
+                }
+                Op::Get(name) => {
+                    let name = match &frame.closure.proto.chunk.constants[name as usize] {
+                        Value::String(name) => name.clone(),
+                        _ => unreachable!("property names are strings"),
+                    };
+
+                    let value = match self.pop() {
+                        Value::Enum(ty) => match ty.variant(&name) {
+                            Some(variant) if ty.variants[variant].1 == 0 => {
+                                Value::Tagged(Rc::new(Tagged {
+                                    ty,
+                                    variant,
+                                    payload: Vec::new(),
+                                }))
+                            }
+                            Some(variant) => {
+                                Value::Callable(Rc::new(Function::Constructor { ty, variant }))
+                            }
+                            None => {
+                                return Err(at(
+                                    &frame,
+                                    format!("Undefined variant {}.{}.", ty.name, name),
+                                ))
+                            }
+                        },
+                        _ => return Err(at(&frame, "Only enums have properties.".into())),
+                    };
+
+                    self.stack.push(value);
+                }
+                Op::Enum(index) => {
+                    let ty = match &frame.closure.proto.chunk.constants[index as usize] {
+                        Value::Enum(ty) => Enum {
+                            name: ty.name.clone(),
+                            variants: ty.variants.clone(),
+                        },
+                        _ => unreachable!("enum declarations are enums"),
+                    };
+
+                    self.stack.push(Value::Enum(Rc::new(ty)));
+                }
+                Op::Pattern(index) => {
+                    let pattern = &frame.closure.proto.chunk.patterns[index as usize];
+
+                    let ty = match self.pop() {
+                        Value::Enum(ty) => ty,
+                        _ => return Err(at(&frame, format!("{} is not an enum.", pattern.ty))),
+                    };
+
+                    let variant = match ty.variant(&pattern.variant) {
+                        Some(variant) => variant,
+                        None => {
+                            return Err(CompileError::Interpreter(
+                                pattern.variant_place,
+                                format!("Undefined variant {}.{}.", ty.name, pattern.variant),
+                            ))
+                        }
+                    };
+
+                    if ty.variants[variant].1 != pattern.bindings {
+                        return Err(CompileError::Interpreter(
+                            pattern.variant_place,
+                            format!(
+                                "Expected {} arguments but got {}.",
+                                ty.variants[variant].1, pattern.bindings
+                            ),
+                        ));
+                    }
+
+                    let matches = matches!(
+                        self.peek(),
+                        Value::Tagged(t) if Rc::ptr_eq(&t.ty, &ty) && t.variant == variant
+                    );
+                    self.stack.push(Value::Boolean(matches));
+                }
+                Op::Destructure => {
+                    let payload = match self.peek() {
+                        Value::Tagged(t) => t.payload.clone(),
+                        _ => unreachable!("only enum values match a variant"),
+                    };
+
+                    self.stack.extend(payload);
+                }
+            }
+        }
+    }
+
+    /// The upvalue for the stack slot, shared by every closure that captures it.
+    fn capture(&mut self, slot: usize) -> Rc<RefCell<Upvalue>> {
+        let existing = self
+            .open_upvalues
+            .iter()
+            .find(|upvalue| matches!(*upvalue.borrow(), Upvalue::Open(s) if s == slot));
+
+        if let Some(upvalue) = existing {
+            return upvalue.clone();
+        }
+
+        let upvalue = self.heap.upvalue(Upvalue::Open(slot));
+        self.open_upvalues.push(upvalue.clone());
+        upvalue
+    }
+
+    /// Moves the values of the slots from `from` up out of the stack.
+    fn close_upvalues(&mut self, from: usize) {
+        let stack = &self.stack;
+
+        self.open_upvalues.retain(|upvalue| {
+            let mut upvalue = upvalue.borrow_mut();
+
+            match *upvalue {
+                Upvalue::Open(slot) if slot >= from => {
+                    *upvalue = Upvalue::Closed(stack[slot].clone());
+                    false
+                }
+                _ => true,
+            }
+        });
+    }
+}
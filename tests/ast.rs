@@ -0,0 +1,116 @@
+//! Expressions live in a tree, found by id, that functions keep with them.
+
+use lux::{
+    errors::CompileError,
+    expr::{Ast, Expr, Value},
+    interpreter::Interpreter,
+    parser::Parser,
+    resolver::Resolver,
+    scanner::Scanner,
+    statements::Statement,
+};
+
+use std::rc::Rc;
+
+fn parse(source: &str, ast: &mut Ast) -> Vec<Statement> {
+    let mut statements = Parser::new(Scanner::new(source), ast).parse().unwrap();
+    Resolver::resolve(&mut statements, ast).unwrap();
+    statements
+}
+
+#[test]
+fn expressions_have_the_span_of_their_tokens() {
+    let source = "print a * f(b, 2) + -c;";
+    let mut ast = Ast::default();
+    let statements = parse(source, &mut ast);
+    let text = |(start, end): (usize, usize)| &source[start..end];
+
+    let Statement::Print(sum) = statements[0] else {
+        panic!("unexpected statements: {:?}", statements)
+    };
+    let Expr::Binary(product, plus, negated) = ast[sum] else {
+        panic!("not a sum: {:?}", ast[sum])
+    };
+    let Expr::Binary(_, _, call) = ast[product] else {
+        panic!("not a product: {:?}", ast[product])
+    };
+
+    assert_eq!(text(ast.place(sum)), "a * f(b, 2) + -c");
+    assert_eq!(text(ast.place(product)), "a * f(b, 2)");
+    assert_eq!(text(ast.place(call)), "f(b, 2)");
+    assert_eq!(text(ast.place(negated)), "-c");
+    assert_eq!(ast[plus].lexeme, "+");
+}
+
+/// `source` parsed on its own, ready to run.
+fn program(source: &str) -> (Vec<Statement>, Rc<Ast>) {
+    let mut ast = Ast::default();
+    let statements = parse(source, &mut ast);
+    (statements, Rc::new(ast))
+}
+
+#[test]
+fn functions_run_from_the_tree_they_were_declared_in() {
+    let mut interpreter = Interpreter::default();
+
+    let first = "fn next(n) { return n + 1; }
+    fn add(a, b) { if (b == 0) return a; return add(next(a), b - 1); }";
+    let (declarations, ast) = program(first);
+    interpreter.interpret(&declarations, &ast).unwrap();
+    drop(ast);
+
+    let second = "var a, b = add(1, 3);";
+    let (unpack, ast) = program(second);
+
+    match interpreter.interpret(&unpack, &ast) {
+        Err(CompileError::Interpreter(place, msg)) => {
+            assert_eq!(&second[place.0..place.1], "a, b");
+            assert_eq!(msg, "Can only unpack tuples, got 4.");
+        }
+        other => panic!("expected a runtime error, got {:?}", other),
+    }
+
+    let (calls, ast) = program("var a = next(1); next(nil);");
+
+    match interpreter.interpret(&calls, &ast) {
+        Err(CompileError::Interpreter(place, msg)) => {
+            assert_eq!(&first[place.0..place.1], "+");
+            assert_eq!(msg, "No Add for the given values");
+        }
+        other => panic!("expected a runtime error, got {:?}", other),
+    }
+}
+
+#[test]
+fn places_are_byte_offsets_in_the_source() {
+    let source = "var größe = \"€\"; print größe + straße;";
+    let mut ast = Ast::default();
+    let statements = parse(source, &mut ast);
+    let text = |(start, end): (usize, usize)| &source[start..end];
+
+    let Statement::Print(sum) = statements[1] else {
+        panic!("unexpected statements: {:?}", statements)
+    };
+    let Expr::Binary(lhs, _, rhs) = ast[sum] else {
+        panic!("not a sum: {:?}", ast[sum])
+    };
+
+    assert_eq!(text(ast.place(lhs)), "größe");
+    assert_eq!(text(ast.place(rhs)), "straße");
+    assert_eq!(text(ast.place(sum)), "größe + straße");
+}
+
+#[test]
+fn string_literals_are_read_from_the_source() {
+    let mut ast = Ast::default();
+    let statements = parse("var s = \"größe: 1 €\";", &mut ast);
+
+    let Statement::Var(var) = &statements[0] else {
+        panic!("unexpected statements: {:?}", statements)
+    };
+    let Expr::Literal(value) = ast[var.initializer.unwrap()] else {
+        panic!("not a literal")
+    };
+
+    assert_eq!(ast[value], Value::String(Rc::new("größe: 1 €".into())));
+}
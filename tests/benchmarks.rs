@@ -0,0 +1,210 @@
+//! Timing checks, `cargo test --release -- --ignored`.
+
+use std::{
+    env, fs,
+    process::Command,
+    time::{Duration, Instant},
+};
+
+/// How long the script takes to run with the tree walking interpreter.
+fn time(name: &str, source: &str, expected: &str) -> Duration {
+    time_with(env!("CARGO_BIN_EXE_lux"), name, &[], source, expected)
+}
+
+/// How long the script takes to run with the `lux` binary and the flags.
+fn time_with(lux: &str, name: &str, flags: &[&str], source: &str, expected: &str) -> Duration {
+    let script = env::temp_dir().join(format!("lux_bench_{}.lux", name));
+    fs::write(&script, source).unwrap();
+
+    let start = Instant::now();
+    let output = Command::new(lux).args(flags).arg(&script).output().unwrap();
+
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+    start.elapsed()
+}
+
+/// A loop whose body has `size` statements that never run.
+fn loop_with_dead_code(size: usize) -> String {
+    let dead = "{ var a = 1; a = a + 1; print a; }\n".repeat(size);
+
+    format!(
+        "var never = false;
+        var i = 0;
+        while (i < 200000) {{
+            if (never) {{ {} }}
+            i = i + 1;
+        }}
+        print i;",
+        dead
+    )
+}
+
+#[test]
+#[ignore]
+fn loops_do_not_scale_with_the_size_of_their_body() {
+    let small = time("small_body", &loop_with_dead_code(1), "200000\n");
+    let large = time("large_body", &loop_with_dead_code(500), "200000\n");
+
+    assert!(large < small * 2, "small: {:?}, large: {:?}", small, large);
+}
+
+/// A loop reading a string of `2^doublings` characters.
+fn loop_reading_string(doublings: usize) -> String {
+    format!(
+        "var s = \"x\";
+        var k = 0;
+        while (k < {}) {{ s = s + s; k = k + 1; }}
+        var i = 0;
+        while (i < 200000) {{
+            var t = s;
+            i = i + 1;
+        }}
+        print i;",
+        doublings
+    )
+}
+
+#[test]
+#[ignore]
+fn reading_strings_does_not_scale_with_their_length() {
+    let short = time("short_string", &loop_reading_string(0), "200000\n");
+    let long = time("long_string", &loop_reading_string(20), "200000\n");
+
+    assert!(long < short * 2, "short: {:?}, long: {:?}", short, long);
+}
+
+/// Arithmetic on numbers only, where the size of a value matters most.
+const NUMERIC: &str = "fn fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }
+var sum = 0;
+var i = 0;
+while (i < 1000000) {
+    sum = sum + i * 2 % 7 + i / 4;
+    i = i + 1;
+}
+print sum > 0;
+print fib(25);";
+
+#[test]
+#[ignore]
+fn numeric_code_runs_in_time() {
+    let tree = time("numeric", NUMERIC, "true\n75025\n");
+
+    assert!(tree.as_secs() < 5, "{:?}", tree);
+}
+
+/// Functions, enum values and tuples passed around, every call copies them.
+const COPIES: &str = "enum Shape { Circle(r), Rect(w, h) }
+fn id(x) { return x; }
+var f = id;
+var s = Shape.Rect(1, 2);
+var t = (1, \"a\", s);
+var i = 0;
+var g = nil;
+while (i < 300000) {
+    g = id(f);
+    var u = id(t);
+    var v = id(s);
+    i = i + 1;
+}
+print i;";
+
+/// The median of 7 runs.
+fn median(lux: &str, name: &str, backend: &str, source: &str, expected: &str) -> Duration {
+    let flag = format!("--backend={}", backend);
+    let mut times: Vec<_> = (0..7)
+        .map(|_| time_with(lux, name, &[&flag], source, expected))
+        .collect();
+
+    times.sort();
+    times[3]
+}
+
+/// Compares values as handles of 16 bytes with the fat values they replaced,
+/// run by a `lux` built from the commit before them:
+///
+/// ```text
+/// git worktree add /tmp/lux-fat 380c8c3^
+/// cargo build --release --manifest-path /tmp/lux-fat/Cargo.toml
+/// LUX_BASELINE=/tmp/lux-fat/target/release/lux cargo test --release -- --ignored --nocapture
+/// ```
+///
+/// The medians measured when the values shrank, fat then handles:
+///
+/// | script  | tree walker     | virtual machine |
+/// |---------|-----------------|-----------------|
+/// | numeric | 1.01 s, 1.00 s  | 0.53 s, 0.33 s  |
+/// | copies  | 0.53 s, 0.52 s  | 0.20 s, 0.14 s  |
+///
+/// The tree walker spends its time walking the tree and looking up
+/// environments, the size of values only shows in the virtual machine.
+#[test]
+#[ignore]
+fn values_are_cheaper_than_the_baseline() {
+    let baseline = match env::var("LUX_BASELINE") {
+        Ok(baseline) => baseline,
+        Err(_) => return println!("LUX_BASELINE isn't set, nothing to compare with"),
+    };
+    let lux = env!("CARGO_BIN_EXE_lux");
+
+    let scripts = [
+        ("numeric", NUMERIC, "true\n75025\n"),
+        ("copies", COPIES, "300000\n"),
+    ];
+
+    for (name, source, expected) in scripts {
+        for backend in ["tree", "vm"] {
+            let before = median(&baseline, name, backend, source, expected);
+            let after = median(lux, name, backend, source, expected);
+
+            println!("{} {}: {:?} before, {:?} now", name, backend, before, after);
+
+            if backend == "vm" {
+                assert!(after < before, "{} {}", name, backend);
+            }
+        }
+    }
+}
+
+/// About `megabytes` of declarations with a bit of everything the scanner
+/// has to tell apart.
+fn generated_source(megabytes: usize) -> String {
+    let mut source = String::new();
+    let mut i = 0;
+
+    while source.len() < megabytes << 20 {
+        source.push_str(&format!(
+            "/// Doc comment of f{i}.
+            fn f{i}(a, b) {{
+                // A plain comment
+                var name = \"function number {i}\";
+                if (a >= 1.5 and b != nil) {{ return a * b + {i} % 7; }}
+                /* a block /* nested */ comment */
+                return name;
+            }}
+            "
+        ));
+        i += 1;
+    }
+
+    source
+}
+
+#[test]
+#[ignore]
+fn scanning_keeps_up_with_large_files() {
+    let source = generated_source(8);
+
+    // The lexemes the parser pulls, before their text is interned
+    let start = Instant::now();
+    let mut tokens = 0;
+    for lexeme in lux::scanner::Scanner::new(&source) {
+        lexeme.unwrap();
+        tokens += 1;
+    }
+    let elapsed = start.elapsed();
+
+    let throughput = source.len() as f64 / (1 << 20) as f64 / elapsed.as_secs_f64();
+    println!("{} tokens, {:.0} MB/s ({:?})", tokens, throughput, elapsed);
+
+    assert!(throughput > 50.0, "{:.0} MB/s", throughput);
+}
@@ -0,0 +1,107 @@
+//! `--max-steps` and `--timeout` stop scripts that run for too long.
+
+mod common;
+
+use lux::{
+    budget::Interrupt, errors::CompileError, expr::Ast, interpreter::Interpreter, parser::Parser,
+    resolver::Resolver, scanner::Scanner, statements::Statement, vm::Vm,
+};
+
+use std::{rc::Rc, time::Duration};
+
+fn parse(source: &str) -> (Vec<Statement>, Rc<Ast>) {
+    let mut ast = Ast::default();
+    let mut statements = Parser::new(Scanner::new(source), &mut ast).parse().unwrap();
+    Resolver::resolve(&mut statements, &mut ast).unwrap();
+    (statements, Rc::new(ast))
+}
+
+#[test]
+fn endless_loops_run_out_of_steps() {
+    for backend in ["--backend=tree", "--backend=vm"] {
+        let output = common::run_once(
+            "max_steps",
+            &[backend, "--max-steps=1000"],
+            "print \"start\";
+            while (true) {}",
+        );
+
+        assert_eq!(output.stdout, "start\n");
+        assert!(output
+            .stderr
+            .contains("Interrupted: the script took more than 1000 steps"));
+    }
+}
+
+#[test]
+fn statements_without_expressions_take_steps() {
+    let source = format!("{}print \"end\";", "var a;\n".repeat(2000));
+
+    for backend in ["--backend=tree", "--backend=vm"] {
+        let output = common::run_once("statement_steps", &[backend, "--max-steps=1000"], &source);
+
+        assert!(output.stdout.is_empty(), "{}", backend);
+        assert!(output
+            .stderr
+            .contains("Interrupted: the script took more than 1000 steps"));
+    }
+}
+
+#[test]
+fn endless_loops_time_out() {
+    for backend in ["--backend=tree", "--backend=vm"] {
+        let output = common::run_once(
+            "timeout",
+            &[backend, "--timeout=100"],
+            "fn spin(n) { return spin(n + 1); }
+            spin(0);",
+        );
+
+        assert!(output
+            .stderr
+            .contains("Interrupted: the script ran for more than 100 ms"));
+    }
+}
+
+#[test]
+fn scripts_within_the_budget_finish() {
+    let output = common::run(
+        "within_budget",
+        &["--max-steps=100000", "--timeout=10000"],
+        "var i = 0;
+        while (i < 100) i = i + 1;
+        print i;",
+    );
+
+    assert_eq!(output.stdout, "100\n");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn hosts_can_tell_interrupts_from_errors() {
+    let (statements, ast) = parse("var i = 0; while (true) i = i + 1;");
+
+    let tree = Interpreter::default()
+        .with_max_steps(500)
+        .interpret(&statements, &ast);
+    let vm = Vm::default()
+        .with_timeout(Duration::from_millis(50))
+        .interpret(&statements, &ast);
+
+    assert!(matches!(
+        tree,
+        Err(CompileError::Interrupted(_, Interrupt::Steps(500)))
+    ));
+    assert!(matches!(
+        vm,
+        Err(CompileError::Interrupted(_, Interrupt::Timeout(_)))
+    ));
+
+    // Every run gets the whole budget again
+    let mut interpreter = Interpreter::default().with_max_steps(500);
+    let (short, ast) = parse("var j = 0; while (j < 10) j = j + 1;");
+
+    for _ in 0..10 {
+        assert!(interpreter.interpret(&short, &ast).is_ok());
+    }
+}
@@ -0,0 +1,102 @@
+//! Natives can only use what the script was allowed to, `--allow-*`.
+
+mod common;
+
+use lux::{
+    errors::CompileError,
+    expr::Ast,
+    interpreter::Interpreter,
+    natives::{Capabilities, Capability},
+    parser::Parser,
+    scanner::Scanner,
+};
+
+use std::{env, fs, path::PathBuf, rc::Rc};
+
+/// A directory with `in.txt` in it, and the script to read it.
+fn sandbox(name: &str) -> (PathBuf, String) {
+    let dir = env::temp_dir().join(format!("lux_test_{}", name));
+    fs::create_dir_all(dir.join("data")).unwrap();
+    fs::write(dir.join("data/in.txt"), "hello").unwrap();
+
+    let data = dir.join("data").canonicalize().unwrap();
+    let source = format!(
+        "print read(\"{0}/in.txt\");
+        write(\"{0}/out.txt\", (1, \"two\"));
+        print read(\"{0}/out.txt\");
+        print read(\"{0}/../secret.txt\");",
+        data.display()
+    );
+
+    (data, source)
+}
+
+#[test]
+fn natives_need_permission() {
+    let (data, source) = sandbox("denied");
+    let output = common::run("denied", &[], &source);
+
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.contains(&format!(
+        "Permission denied: fs.read for {}/in.txt.",
+        data.display()
+    )));
+
+    let output = common::run(
+        "denied_env",
+        &[],
+        "print clock() > 0;
+        print env(\"HOME\");",
+    );
+
+    assert_eq!(output.stdout, "true\n");
+    assert!(output.stderr.contains("Permission denied: env."));
+}
+
+#[test]
+fn files_can_be_used_inside_allowed_directories() {
+    let (data, source) = sandbox("allowed");
+    let read = format!("--allow-read={}", data.display());
+    let write = format!("--allow-write={}", data.display());
+
+    let output = common::run("allowed", &[&read, &write], &source);
+
+    assert_eq!(output.stdout, "hello\n(1, \"two\")\n");
+    assert!(output.stderr.contains("Permission denied: fs.read for "));
+    assert!(output.stderr.contains("secret.txt."));
+}
+
+#[test]
+fn everything_can_be_allowed() {
+    let output = common::run(
+        "allow_all",
+        &["--allow-all"],
+        "print exec(\"echo hi\");
+        print env(\"LUX_TEST_UNSET_VARIABLE\");",
+    );
+
+    assert_eq!(output.stdout, "hi\n\nnil\n");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn embedders_choose_the_capabilities() {
+    let mut ast = Ast::default();
+    let statements = Parser::new(Scanner::new("clock();"), &mut ast)
+        .parse()
+        .unwrap();
+    let ast = Rc::new(ast);
+
+    let denied = Interpreter::default()
+        .with_capabilities(Capabilities::none())
+        .interpret(&statements, &ast);
+    let allowed = Interpreter::default()
+        .with_capabilities(Capabilities::none().allow(Capability::Clock))
+        .interpret(&statements, &ast);
+
+    match denied {
+        Err(CompileError::Interpreter(_, msg)) => assert_eq!(msg, "Permission denied: clock."),
+        other => panic!("expected a runtime error, got {:?}", other),
+    }
+    assert!(allowed.is_ok());
+}
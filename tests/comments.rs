@@ -0,0 +1,86 @@
+//! Block comments and doc comments.
+
+use lux::{
+    errors::CompileError, expr::Ast, parser::Parser, scanner::Scanner, statements::Statement,
+};
+
+fn parse(source: &str) -> Vec<Statement> {
+    Parser::new(Scanner::new(source), &mut Ast::default())
+        .parse()
+        .unwrap()
+}
+
+#[test]
+fn block_comments_nest() {
+    let statements = parse(
+        "/* outer /* inner */ still a comment */
+        print 1; /* between */ print /* inside */ 2;",
+    );
+
+    assert_eq!(statements.len(), 2);
+}
+
+#[test]
+fn unterminated_comment_points_at_opening_delimiter() {
+    let errors = Scanner::new("print 1;\n/* a /* b */ c")
+        .scan_tokens()
+        .unwrap_err();
+
+    match errors.as_slice() {
+        [CompileError::Scanner(place, msg)] => {
+            assert_eq!(*place, (9, 11));
+            assert_eq!(msg, "Unterminated comment");
+        }
+        other => panic!("unexpected errors: {:?}", other),
+    }
+}
+
+#[test]
+fn doc_comments_attach_to_declarations() {
+    let statements = parse(
+        "/// Adds two numbers.
+        /// Works with any number.
+        fn add(a, b) { return a + b; }
+
+        /// The answer.
+        var answer = add(40, 2);
+
+        /// Shapes.
+        enum Shape { Circle(r) }
+
+        // Just a comment
+        //// Also just a comment
+        fn undocumented() {}
+
+        /// Not attached to statements.
+        print answer;",
+    );
+
+    let docs: Vec<_> = statements
+        .iter()
+        .map(|stmt| match stmt {
+            Statement::Function(f) => f.doc.as_deref(),
+            Statement::Var(v) => v.doc.as_deref(),
+            Statement::Enum(e) => e.doc.as_deref(),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        docs,
+        [
+            Some("Adds two numbers.\nWorks with any number."),
+            Some("The answer."),
+            Some("Shapes."),
+            None,
+            None,
+        ]
+    );
+}
+
+#[test]
+fn doc_comments_inside_expressions_are_ignored() {
+    let statements = parse("print 1 +\n/// stray\n2;");
+
+    assert_eq!(statements.len(), 1);
+}
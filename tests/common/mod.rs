@@ -0,0 +1,81 @@
+// Each test crate uses a different part of the helpers
+#![allow(dead_code)]
+
+use std::{
+    env, fs,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+/// Output of running a script with the `lux` binary.
+pub struct Output {
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Runs `source` as the script `name` with the given command line flags, with
+/// both backends. They must agree on the output.
+pub fn run(name: &str, flags: &[&str], source: &str) -> Output {
+    let script = env::temp_dir().join(format!("lux_test_{}.lux", name));
+    fs::write(&script, source).unwrap();
+
+    let tree = run_with(&script, flags);
+    let vm = run_with(&script, &[flags, &["--backend=vm"]].concat());
+
+    assert_eq!(
+        tree.stdout, vm.stdout,
+        "the backends printed different output"
+    );
+    assert_eq!(
+        tree.stderr, vm.stderr,
+        "the backends reported different errors"
+    );
+
+    tree
+}
+
+/// Runs `source` once, for output that depends on the backend.
+pub fn run_once(name: &str, flags: &[&str], source: &str) -> Output {
+    let script = env::temp_dir().join(format!("lux_test_{}.lux", name));
+    fs::write(&script, source).unwrap();
+
+    run_with(&script, flags)
+}
+
+fn run_with(script: &Path, flags: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_lux"))
+        .args(flags)
+        .arg(script)
+        .output()
+        .unwrap();
+
+    Output {
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap(),
+    }
+}
+
+/// Types `input` into the REPL of the given backend and closes it.
+pub fn repl(flags: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lux"))
+        .args(flags)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    Output {
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap(),
+    }
+}
@@ -0,0 +1,154 @@
+//! Enums with payloads and the `match` statements over them.
+
+mod common;
+
+use lux::{errors::CompileError, exhaustiveness, expr::Ast, parser::Parser, scanner::Scanner};
+
+/// The messages of the exhaustiveness warnings.
+fn warnings(source: &str) -> Vec<String> {
+    let mut ast = Ast::default();
+    let statements = Parser::new(Scanner::new(source), &mut ast).parse().unwrap();
+
+    exhaustiveness::check(&statements)
+        .into_iter()
+        .map(|w| match w {
+            CompileError::Warning(_, _, msg) => msg,
+            other => panic!("unexpected error: {:?}", other),
+        })
+        .collect()
+}
+
+#[test]
+fn values_are_displayed_with_their_enum() {
+    let output = common::run(
+        "enum_display",
+        &[],
+        "enum Shape { Circle(r), Rect(w, h), Empty }
+        print Shape;
+        print Shape.Empty;
+        print Shape.Circle(1);
+        print Shape.Rect(\"a\", (1, 2));
+        print Shape.Circle;",
+    );
+
+    assert_eq!(
+        output.stdout,
+        "<enum Shape>\nShape.Empty\nShape.Circle(1)\nShape.Rect(\"a\", (1, 2))\n<fn Shape.Circle>\n"
+    );
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn values_are_equal_by_variant_and_payload() {
+    let output = common::run(
+        "enum_equality",
+        &[],
+        "enum Shape { Circle(r), Empty }
+        enum Other { Circle(r), Empty }
+        print Shape.Empty == Shape.Empty;
+        print Shape.Circle(1) == Shape.Circle(1);
+        print Shape.Circle(1) == Shape.Circle(2);
+        print Shape.Circle(1) == Shape.Empty;
+        print Shape.Empty == Other.Empty;
+        print Shape.Circle(1) == Other.Circle(1);",
+    );
+
+    assert_eq!(output.stdout, "true\ntrue\nfalse\nfalse\nfalse\nfalse\n");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn match_binds_the_payload() {
+    let output = common::run(
+        "enum_bindings",
+        &[],
+        "enum Shape { Circle(r), Rect(w, h), Empty }
+        fn area(s) {
+            match (s) {
+                Shape.Circle(r) => return 3 * r * r;
+                Shape.Rect(w, h) => return w * h;
+                Shape.Empty => return 0;
+            }
+        }
+        print area(Shape.Circle(2));
+        print area(Shape.Rect(2, 5));
+        print area(Shape.Empty);
+        match (Shape.Rect(1, 2)) {
+            Shape.Rect(h, w) => print (h, w);
+            else => print \"other\";
+        }",
+    );
+
+    assert_eq!(output.stdout, "12\n10\n0\n(1, 2)\n");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn missing_variants_are_reported() {
+    assert_eq!(
+        warnings(
+            "enum Shape { Circle(r), Rect(w, h), Empty }
+            fn f(s) {
+                match (s) {
+                    Shape.Circle(r) => print r;
+                }
+            }"
+        ),
+        ["Non-exhaustive match, missing: Shape.Rect, Shape.Empty."]
+    );
+
+    assert!(warnings(
+        "enum Shape { Circle(r), Empty }
+        match (Shape.Empty) {
+            Shape.Circle(r) => print r;
+            else => print 0;
+        }"
+    )
+    .is_empty());
+}
+
+#[test]
+fn enums_declared_in_any_statement_are_checked() {
+    assert_eq!(
+        warnings(
+            "if (true) {
+                enum A { X, Y }
+                match (A.X) { A.X => print 1; }
+            }
+            while (false) {
+                enum B { X, Y }
+                match (B.X) { B.Y => print 1; }
+            }
+            match (1) {
+                else => {
+                    enum C { X, Y }
+                    match (C.X) { C.X => print 1; }
+                }
+            }"
+        ),
+        [
+            "Non-exhaustive match, missing: A.Y.",
+            "Non-exhaustive match, missing: B.X.",
+            "Non-exhaustive match, missing: C.Y.",
+        ]
+    );
+}
+
+#[test]
+fn variant_names_are_unique() {
+    let mut ast = Ast::default();
+    let errors = Parser::new(
+        Scanner::new("enum Shape { Circle(r), Empty, Circle }"),
+        &mut ast,
+    )
+    .parse()
+    .unwrap_err();
+
+    match &errors[..] {
+        [CompileError::Parser(place, msg)] => {
+            assert_eq!(msg, "Already a variant named Circle in Shape.");
+            assert_eq!(*place, (31, 37));
+        }
+        other => panic!("expected one parser error, got {:?}", other),
+    }
+}
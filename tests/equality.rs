@@ -0,0 +1,238 @@
+//! Equality, identity and hashing rules for every pair of value kinds.
+
+mod common;
+
+use lux::{
+    environment::Environment,
+    expr::{Ast, Enum, Function, Tagged, Value},
+    parser::Parser,
+    scanner::Scanner,
+    statements::Statement,
+};
+
+use std::{
+    cell::RefCell,
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
+
+/// A value with the class of values it is `==` to (`None` for not even
+/// itself) and the class of values it is `same` as.
+struct Sample {
+    name: &'static str,
+    value: Value,
+    equal: Option<u32>,
+    same: u32,
+}
+
+fn shape() -> Rc<Enum> {
+    Rc::new(Enum {
+        name: "Shape".into(),
+        variants: vec![("Circle".into(), 1), ("Empty".into(), 0)],
+    })
+}
+
+fn tagged(ty: &Rc<Enum>, variant: usize, payload: Vec<Value>) -> Value {
+    Value::Tagged(Rc::new(Tagged {
+        ty: ty.clone(),
+        variant,
+        payload,
+    }))
+}
+
+fn user_function() -> Value {
+    let mut ast = Ast::default();
+    let mut statements = Parser::new(Scanner::new("fn f() {}"), &mut ast)
+        .parse()
+        .unwrap();
+
+    match statements.remove(0) {
+        Statement::Function(declaration) => Value::Callable(Rc::new(Function::User {
+            declaration,
+            closure: Rc::new(RefCell::new(Environment::default())),
+            ast: Rc::new(ast),
+        })),
+        _ => unreachable!(),
+    }
+}
+
+fn native(name: &'static str) -> Value {
+    Value::Callable(Rc::new(Function::Native {
+        name,
+        arity: 0,
+        body: Box::new(|_, _| Ok(Value::Nil)),
+    }))
+}
+
+fn string(s: &str) -> Value {
+    Value::String(Rc::new(s.into()))
+}
+
+fn samples() -> Vec<Sample> {
+    let (a, b) = (shape(), shape());
+    let pair = Rc::new(vec![Value::Number(1.0), string("a")]);
+    let f = user_function();
+    let circle = tagged(&a, 0, vec![Value::Number(1.0)]);
+
+    let samples = vec![
+        ("nil", Value::Nil, Some(0), 0),
+        ("true", Value::Boolean(true), Some(1), 1),
+        ("false", Value::Boolean(false), Some(2), 2),
+        ("0", Value::Number(0.0), Some(3), 3),
+        ("-0", Value::Number(-0.0), Some(3), 4),
+        ("1", Value::Number(1.0), Some(5), 5),
+        ("NaN", Value::Number(f64::NAN), None, 6),
+        ("\"\"", string(""), Some(7), 7),
+        ("\"1\"", string("1"), Some(8), 8),
+        ("\"a\"", string("a"), Some(9), 9),
+        ("(1, \"a\")", Value::Tuple(pair.clone()), Some(10), 10),
+        ("(1, \"a\") again", Value::Tuple(pair), Some(10), 10),
+        (
+            "(1, \"a\") copy",
+            Value::Tuple(Rc::new(vec![Value::Number(1.0), string("a")])),
+            Some(10),
+            11,
+        ),
+        (
+            "(1,)",
+            Value::Tuple(Rc::new(vec![Value::Number(1.0)])),
+            Some(12),
+            12,
+        ),
+        ("enum A", Value::Enum(a.clone()), Some(13), 13),
+        ("enum B", Value::Enum(b.clone()), Some(14), 14),
+        ("A.Circle(1)", circle.clone(), Some(15), 15),
+        ("A.Circle(1) again", circle, Some(15), 15),
+        (
+            "A.Circle(1) copy",
+            tagged(&a, 0, vec![Value::Number(1.0)]),
+            Some(15),
+            25,
+        ),
+        (
+            "A.Circle(2)",
+            tagged(&a, 0, vec![Value::Number(2.0)]),
+            Some(16),
+            16,
+        ),
+        ("A.Empty", tagged(&a, 1, Vec::new()), Some(17), 17),
+        (
+            "B.Circle(1)",
+            tagged(&b, 0, vec![Value::Number(1.0)]),
+            Some(18),
+            18,
+        ),
+        ("clock", native("clock"), Some(19), 19),
+        ("same", native("same"), Some(20), 20),
+        ("fn f", f.clone(), Some(21), 21),
+        ("fn f again", f, Some(21), 21),
+        ("fn f redeclared", user_function(), Some(22), 22),
+        (
+            "A.Circle",
+            Value::Callable(Rc::new(Function::Constructor { ty: a, variant: 0 })),
+            Some(23),
+            23,
+        ),
+        (
+            "B.Circle",
+            Value::Callable(Rc::new(Function::Constructor { ty: b, variant: 0 })),
+            Some(24),
+            24,
+        ),
+    ];
+
+    samples
+        .into_iter()
+        .map(|(name, value, equal, same)| Sample {
+            name,
+            value,
+            equal,
+            same,
+        })
+        .collect()
+}
+
+fn hash(value: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn equality() {
+    let samples = samples();
+
+    for a in &samples {
+        for b in &samples {
+            let expected = a.equal.is_some() && a.equal == b.equal;
+
+            assert_eq!(a.value == b.value, expected, "{} == {}", a.name, b.name);
+            assert_eq!(a.value != b.value, !expected, "{} != {}", a.name, b.name);
+        }
+    }
+}
+
+#[test]
+fn identity() {
+    let samples = samples();
+
+    for a in &samples {
+        for b in &samples {
+            let expected = a.same == b.same;
+
+            assert_eq!(
+                a.value.same(&b.value),
+                expected,
+                "same({}, {})",
+                a.name,
+                b.name
+            );
+        }
+    }
+}
+
+#[test]
+fn equal_values_hash_the_same() {
+    let samples = samples();
+
+    for a in &samples {
+        for b in &samples {
+            if a.value == b.value {
+                assert_eq!(hash(&a.value), hash(&b.value), "{} and {}", a.name, b.name);
+            }
+        }
+    }
+}
+
+#[test]
+fn equality_in_scripts() {
+    let output = common::run(
+        "equality",
+        &[],
+        r#"
+        fn g() {}
+        var f = g;
+        print f == f;
+        print f == g;
+        print same(f, g);
+        print clock == clock;
+        print 1 == "1";
+        print nil == false;
+        print 0 / 0 == 0 / 0;
+        print same(0 / 0, 0 / 0);
+        print same(0, -0);
+        enum Shape { Circle(r) }
+        var c = Shape.Circle(1);
+        print same(c, c);
+        print same(c, Shape.Circle(1));
+        print c == Shape.Circle(1);
+        "#,
+    );
+
+    assert_eq!(
+        output.stdout,
+        "true\ntrue\ntrue\ntrue\nfalse\nfalse\nfalse\ntrue\nfalse\ntrue\nfalse\ntrue\n"
+    );
+    assert!(output.stderr.is_empty());
+}
@@ -0,0 +1,78 @@
+//! Closures that reference themselves are freed by the cycle collector.
+
+mod common;
+
+const MAKE: &str = "fn make() {
+    fn again(n) { if (n == 0) return 0; return again(n - 1); }
+    return again;
+}
+";
+
+#[test]
+fn gc_frees_unreachable_cycles() {
+    let output = common::run(
+        "gc_cycles",
+        &[],
+        &format!(
+            "{}
+            var i = 0;
+            while (i < 100) {{ make()(3); i = i + 1; }}
+            print gc() > 0;
+            print gc();",
+            MAKE
+        ),
+    );
+
+    assert_eq!(output.stdout, "true\n0\n");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn gc_keeps_reachable_closures() {
+    let output = common::run(
+        "gc_reachable",
+        &[],
+        &format!(
+            "{}
+            fn counter() {{
+                var count = 0;
+                fn next() {{ count = count + 1; return count; }}
+                return next;
+            }}
+            var again = make();
+            var next = counter();
+            next();
+            var pair = (make(), counter());
+            gc();
+            var first, second = pair;
+            print again(5);
+            print next();
+            print first(1);
+            print second();",
+            MAKE
+        ),
+    );
+
+    assert_eq!(output.stdout, "0\n2\n0\n1\n");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn cycles_are_collected_while_running() {
+    for backend in ["--backend=tree", "--backend=vm"] {
+        let output = common::run_once(
+            "gc_stats",
+            &[backend, "--gc-stats"],
+            &format!(
+                "{}
+                var i = 0;
+                while (i < 5000) {{ make(); i = i + 1; }}",
+                MAKE
+            ),
+        );
+
+        assert!(output.stderr.starts_with("gc: "), "{}", output.stderr);
+        assert!(!output.stderr.starts_with("gc: 0 collections"));
+        assert!(output.stderr.ends_with(" live\n"));
+    }
+}
@@ -0,0 +1,264 @@
+//! Scripts compiled ahead of time to `.luxc` files.
+
+use lux::{
+    chunk::Op,
+    luxc::{self, Program, VERSION},
+};
+
+use std::{env, fs, path::PathBuf, process::Command, rc::Rc};
+
+struct Output {
+    stdout: String,
+    stderr: String,
+    code: Option<i32>,
+}
+
+fn lux(args: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_lux"))
+        .args(args)
+        .output()
+        .unwrap();
+
+    Output {
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap(),
+        code: output.status.code(),
+    }
+}
+
+/// Compiles `source` and returns the path of the `.luxc` file.
+fn compile(name: &str, source: &str) -> PathBuf {
+    let script = env::temp_dir().join(format!("lux_test_{}.lux", name));
+    let compiled = script.with_extension("luxc");
+    fs::write(&script, source).unwrap();
+
+    let output = lux(&["compile", script.to_str().unwrap()]);
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+
+    compiled
+}
+
+#[test]
+fn compiled_scripts_run_like_the_source() {
+    let compiled = compile(
+        "luxc_run",
+        "enum Shape { Circle(r), Square(side) }
+        fn area(s: Shape) -> number {
+            match (s) {
+                Shape.Circle(r) => return 3 * r * r;
+                Shape.Square(side) => return side * side;
+            }
+        }
+        var a, b = (Shape.Circle(1), Shape.Square(2));
+        print (area(a), area(b), \"done\");",
+    );
+
+    let output = lux(&[compiled.to_str().unwrap()]);
+
+    assert_eq!(output.stdout, "(3, 4, \"done\")\n");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn errors_point_at_the_source() {
+    let compiled = compile("luxc_error", "var a = 1;\nprint a + \"b\";");
+
+    let output = lux(&[compiled.to_str().unwrap()]);
+
+    assert!(output.stderr.contains("lux_test_luxc_error.lux:2:9"));
+    assert!(output.stderr.contains("No Add for Number and String"));
+}
+
+#[test]
+fn the_output_can_be_chosen() {
+    let script = env::temp_dir().join("lux_test_luxc_output.lux");
+    let output = env::temp_dir().join("lux_test_luxc_chosen.bin");
+    fs::write(&script, "print 1;").unwrap();
+
+    lux(&[
+        "compile",
+        script.to_str().unwrap(),
+        "-o",
+        output.to_str().unwrap(),
+    ]);
+
+    assert_eq!(lux(&[output.to_str().unwrap()]).stdout, "1\n");
+}
+
+#[test]
+fn other_versions_are_rejected() {
+    let compiled = compile("luxc_version", "print 1;");
+
+    let mut bytes = fs::read(&compiled).unwrap();
+    bytes[4..8].copy_from_slice(&(VERSION + 1).to_le_bytes());
+    fs::write(&compiled, &bytes).unwrap();
+
+    let output = lux(&[compiled.to_str().unwrap()]);
+
+    assert_eq!(output.code, Some(65));
+    assert!(output.stdout.is_empty());
+    assert!(output
+        .stderr
+        .contains(&format!("bytecode version {}", VERSION + 1)));
+    assert!(output.stderr.contains("Compile it again."));
+}
+
+#[test]
+fn truncated_files_are_rejected() {
+    let compiled = compile("luxc_truncated", "print 1;");
+    let bytes = fs::read(&compiled).unwrap();
+
+    for len in [4, 10, bytes.len() / 2, bytes.len() - 1] {
+        assert_eq!(
+            luxc::read(&bytes[..len]).unwrap_err(),
+            "Unexpected end of the file."
+        );
+    }
+
+    assert!(luxc::read(&bytes).is_ok());
+}
+
+/// Runs the compiled file after `edit` changed it.
+fn edited(compiled: &PathBuf, edit: impl FnOnce(&mut Program)) -> Output {
+    let mut program = luxc::read(&fs::read(compiled).unwrap()).unwrap();
+    edit(&mut program);
+
+    let corrupted = compiled.with_extension("corrupted.luxc");
+    fs::write(&corrupted, luxc::write(&program)).unwrap();
+
+    lux(&[corrupted.to_str().unwrap()])
+}
+
+/// Replaces the first instruction of the first function, or of the script,
+/// that `find` matches and runs the file.
+fn corrupt(compiled: &PathBuf, find: fn(&Op) -> bool, replacement: Op) -> Output {
+    edited(compiled, |program| {
+        let function = Rc::get_mut(&mut program.script.chunk.functions[0]).unwrap();
+
+        let op = function
+            .chunk
+            .code
+            .iter_mut()
+            .chain(&mut program.script.chunk.code)
+            .find(|op| find(op))
+            .unwrap();
+        *op = replacement;
+    })
+}
+
+#[test]
+fn corrupted_operands_are_rejected() {
+    let compiled = compile(
+        "luxc_corrupted",
+        "var a = 1;
+        fn f(x) { if (x) return a; return (x, x); }
+        print f(true);",
+    );
+
+    let corruptions = [
+        (
+            corrupt(
+                &compiled,
+                |op| matches!(op, Op::Constant(_)),
+                Op::Constant(99),
+            ),
+            "refers to constant 99",
+        ),
+        (
+            corrupt(
+                &compiled,
+                |op| matches!(op, Op::GetGlobal(_)),
+                Op::GetGlobal(99),
+            ),
+            "refers to global 99",
+        ),
+        (
+            corrupt(
+                &compiled,
+                |op| matches!(op, Op::JumpIfFalse(_)),
+                Op::Jump(9999),
+            ),
+            "refers to instruction 9999",
+        ),
+        (
+            corrupt(&compiled, |op| matches!(op, Op::Call(_)), Op::Call(99)),
+            "refers to call 99",
+        ),
+        (
+            corrupt(
+                &compiled,
+                |op| matches!(op, Op::Closure(_)),
+                Op::Closure(99),
+            ),
+            "refers to function 99",
+        ),
+    ];
+
+    for (output, expected) in corruptions {
+        assert_eq!(output.code, Some(65), "{}", output.stderr);
+        assert!(output.stdout.is_empty());
+        assert!(output.stderr.contains("corrupt .luxc: "));
+        assert!(output.stderr.contains(expected), "{}", output.stderr);
+    }
+}
+
+#[test]
+fn corrupted_stacks_are_rejected() {
+    let compiled = compile(
+        "luxc_stack",
+        "var a = 1;
+        fn f(x) { if (x) return a; return (x, x); }
+        print f(true);",
+    );
+
+    let prepend = |ops: Vec<Op>| {
+        move |program: &mut Program| {
+            let chunk = &mut program.script.chunk;
+            for op in ops {
+                chunk.code.insert(0, op);
+                chunk.spans.insert(0, (0, 0));
+            }
+        }
+    };
+
+    let corruptions = [
+        (
+            edited(&compiled, prepend(vec![Op::GetLocal(99)])),
+            "instruction 0 of script uses more values than the stack has.",
+        ),
+        (
+            edited(&compiled, prepend(vec![Op::Pop, Op::Pop])),
+            "instruction 1 of script uses more values than the stack has.",
+        ),
+        (
+            corrupt(&compiled, |op| matches!(op, Op::Tuple(_)), Op::Tuple(9)),
+            "of f uses more values than the stack has.",
+        ),
+        (
+            corrupt(&compiled, |op| matches!(op, Op::Pop), Op::Destructure),
+            "of f destructures a value that didn't match.",
+        ),
+        (
+            edited(&compiled, |program| {
+                program.script.chunk.spans[0] = (1000, 2000);
+            }),
+            "script points at 1000..2000, outside of the source.",
+        ),
+    ];
+
+    let closure = compile(
+        "luxc_upvalue",
+        "{ var n = 1; fn g() { return n; } print g(); }",
+    );
+    let popped = corrupt(&closure, |op| matches!(op, Op::CloseUpvalue), Op::Pop);
+
+    for (output, expected) in corruptions.into_iter().chain([(
+        popped,
+        "of script pops a captured local without closing it.",
+    )]) {
+        assert_eq!(output.code, Some(65), "{}", output.stderr);
+        assert!(output.stdout.is_empty());
+        assert!(output.stderr.contains("corrupt .luxc: "));
+        assert!(output.stderr.contains(expected), "{}", output.stderr);
+    }
+}
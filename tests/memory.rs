@@ -0,0 +1,167 @@
+//! `--max-memory` stops scripts that use too much memory.
+
+mod common;
+
+use lux::{
+    errors::CompileError, expr::Ast, gc::OutOfMemory, interpreter::Interpreter, parser::Parser,
+    resolver::Resolver, scanner::Scanner, vm::Vm,
+};
+
+use std::{env, fs, rc::Rc};
+
+#[test]
+fn growing_strings_exceed_the_limit() {
+    let output = common::run(
+        "memory_strings",
+        &["--max-memory=1M"],
+        "var s = \"ab\";
+        while (true) s = s + s;",
+    );
+
+    assert!(output.stdout.is_empty());
+    assert!(output
+        .stderr
+        .contains("Memory limit exceeded, the script needs more than 1048576 bytes."));
+    assert!(output.stderr.contains(":2:28"));
+}
+
+#[test]
+fn growing_tuples_exceed_the_limit() {
+    let output = common::run(
+        "memory_tuples",
+        &["--max-memory=64K"],
+        "enum List { Cons(head, tail), Nil }
+        var list = List.Nil;
+        var i = 0;
+        while (true) { list = List.Cons((i, i), list); i = i + 1; }",
+    );
+
+    assert!(output
+        .stderr
+        .contains("Memory limit exceeded, the script needs more than 65536 bytes."));
+}
+
+#[test]
+fn freed_memory_is_available_again() {
+    let output = common::run(
+        "memory_reuse",
+        &["--max-memory=256K"],
+        "fn make() {
+            fn again(n) { if (n == 0) return 0; return again(n - 1); }
+            return again;
+        }
+        var i = 0;
+        while (i < 20000) {
+            var s = \"x\" + \"yyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyy\";
+            var t = (s, s, make());
+            i = i + 1;
+        }
+        print i;",
+    );
+
+    assert_eq!(output.stdout, "20000\n");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn shared_strings_are_counted_once() {
+    // 64 KiB built by doubling and sixteen names for it, then enough
+    // short-lived tuples to make the heap measure what is in use
+    let mut source = String::from(
+        "var s = \"x\";
+        var i = 0;
+        while (i < 16) { s = s + s; i = i + 1; }\n",
+    );
+    for i in 0..16 {
+        source.push_str(&format!("var a{} = s;\n", i));
+    }
+    source.push_str(
+        "var j = 0;
+        while (j < 50000) { var t = (j, j); j = j + 1; }
+        print j;",
+    );
+
+    let output = common::run("memory_shared_strings", &["--max-memory=1M"], &source);
+
+    assert_eq!(output.stdout, "50000\n");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn strings_from_natives_count() {
+    let dir = env::temp_dir().join("lux_test_memory_natives");
+    fs::create_dir_all(&dir).unwrap();
+    let dir = dir.canonicalize().unwrap();
+    let big = dir.join("big.txt");
+    fs::write(&big, "x".repeat(2 << 20)).unwrap();
+
+    let read = format!("--allow-read={}", dir.display());
+    let output = common::run(
+        "memory_read",
+        &["--max-memory=1M", &read],
+        &format!("var s = read(\"{}\");\nprint \"read\";", big.display()),
+    );
+
+    assert!(output.stdout.is_empty());
+    assert!(output
+        .stderr
+        .contains("Memory limit exceeded, the script needs more than 1048576 bytes."));
+
+    let output = common::run(
+        "memory_exec",
+        &["--max-memory=1M", "--allow-run"],
+        &format!("var s = exec(\"cat {}\");\nprint \"ran\";", big.display()),
+    );
+
+    assert!(output.stdout.is_empty());
+    assert!(output
+        .stderr
+        .contains("Memory limit exceeded, the script needs more than 1048576 bytes."));
+}
+
+#[test]
+fn environments_count() {
+    // Every block keeps the one before it alive through the closure, without
+    // a value that takes memory of its own
+    let output = common::run_once(
+        "memory_environments",
+        &["--max-memory=64K", "--max-steps=10000000"],
+        "var keep = nil;
+        while (true) {
+            var k = keep;
+            fn f() { return k; }
+            keep = f;
+        }",
+    );
+
+    assert!(output
+        .stderr
+        .contains("Memory limit exceeded, the script needs more than 65536 bytes."));
+}
+
+#[test]
+fn hosts_can_tell_running_out_of_memory_from_errors() {
+    let mut ast = Ast::default();
+    let mut statements = Parser::new(
+        Scanner::new("var s = \"ab\"; while (true) s = s + s;"),
+        &mut ast,
+    )
+    .parse()
+    .unwrap();
+    Resolver::resolve(&mut statements, &mut ast).unwrap();
+    let ast = Rc::new(ast);
+
+    let tree = Interpreter::default()
+        .with_memory_limit(1 << 16)
+        .interpret(&statements, &ast);
+    let vm = Vm::default()
+        .with_memory_limit(1 << 16)
+        .interpret(&statements, &ast);
+
+    for result in [tree, vm] {
+        assert!(matches!(
+            result,
+            Err(CompileError::OutOfMemory(_, OutOfMemory { limit: 65536 }))
+        ));
+    }
+}
@@ -0,0 +1,78 @@
+//! Permissive and strict (`--strict-numbers`, part of `--strict`) arithmetic.
+
+mod common;
+
+#[test]
+fn permissive_keeps_ieee_results_and_coercions() {
+    let output = common::run(
+        "permissive",
+        &[],
+        r#"
+        print -"3";
+        print 1 / 0;
+        print 0 / 0;
+        print 7 % 3;
+        print 5 % 0;
+        "#,
+    );
+
+    assert_eq!(output.stdout, "-3\ninf\nNaN\n1\nNaN\n");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn strict_rejects_questionable_operations() {
+    let cases = [
+        ("negate_string", "print -\"3\";", "Can't negate a string"),
+        ("division_by_zero", "print 1 / 0;", "Division by zero."),
+        ("modulo_by_zero", "print 5 % 0;", "Division by zero."),
+        (
+            "nan",
+            "var inf = 1; while (inf * 10 > inf) inf = inf * 10; print inf - inf;",
+            "produced NaN.",
+        ),
+    ];
+
+    for (name, source, error) in cases {
+        let output = common::run(name, &["--strict"], source);
+
+        assert!(output.stdout.is_empty(), "{}: {}", name, output.stdout);
+        assert!(output.stderr.contains(error), "{}: {}", name, output.stderr);
+    }
+}
+
+#[test]
+fn strict_allows_regular_arithmetic() {
+    let output = common::run("strict_ok", &["--strict"], "print 7 % 2 + 10 / 4 - -1;");
+
+    assert_eq!(output.stdout, "4.5\n");
+}
+
+#[test]
+fn strict_numbers_and_strict_types_are_separate() {
+    let source = "fn id(n: number) -> number { return n; }
+    print id(\"4\");
+    print 1 / 0;";
+
+    let numbers = common::run("strict_numbers_only", &["--strict-numbers"], source);
+    assert_eq!(numbers.stdout, "4\n");
+    assert!(numbers.stderr.contains("Division by zero."));
+
+    let types = common::run("strict_types_only", &["--strict-types"], source);
+    assert!(types.stdout.is_empty());
+    assert!(types
+        .stderr
+        .contains("Type mismatch: expected number, found string"));
+
+    let types = common::run(
+        "strict_types_arithmetic",
+        &["--strict-types"],
+        "print 1 / 0;",
+    );
+    assert_eq!(types.stdout, "inf\n");
+    assert!(types.stderr.is_empty());
+
+    let both = common::run("strict_both", &["--strict"], source);
+    assert!(both.stdout.is_empty());
+    assert!(both.stderr.contains("Type mismatch"));
+}
@@ -0,0 +1,94 @@
+//! Constant folding and the removal of dead branches.
+
+mod common;
+
+use lux::{
+    expr::{Ast, Expr, ExprId, Value},
+    optimizer::optimize,
+    parser::Parser,
+    resolver::Resolver,
+    scanner::Scanner,
+    statements::Statement,
+};
+
+fn optimized(source: &str) -> (Vec<Statement>, Ast) {
+    let mut ast = Ast::default();
+    let mut statements = Parser::new(Scanner::new(source), &mut ast).parse().unwrap();
+    Resolver::resolve(&mut statements, &mut ast).unwrap();
+
+    optimize(&mut statements, &mut ast);
+    (statements, ast)
+}
+
+/// The expression printed by the last statement.
+fn printed(source: &str) -> (ExprId, Ast) {
+    let (statements, ast) = optimized(source);
+
+    match statements.last() {
+        Some(Statement::Print(expr)) => (*expr, ast),
+        other => panic!("unexpected statements: {:?}", other),
+    }
+}
+
+/// The value of `expr` if it is a literal.
+fn literal(ast: &Ast, expr: ExprId) -> Option<&Value> {
+    match ast[expr] {
+        Expr::Literal(value) => Some(&ast[value]),
+        _ => None,
+    }
+}
+
+#[test]
+fn constants_are_folded_with_the_span_of_the_expression() {
+    let (expr, ast) = printed("print (60 * 60) * 24;");
+
+    assert_eq!(literal(&ast, expr), Some(&Value::Number(86400.0)));
+    assert_eq!(ast.place(expr), (7, 20));
+
+    let (expr, ast) = printed("print \"a\" + \"b\" == \"ab\" and !nil;");
+    assert_eq!(literal(&ast, expr), Some(&Value::Boolean(true)));
+}
+
+#[test]
+fn operations_that_fail_or_depend_on_the_policy_are_kept() {
+    for source in [
+        "print 1 + \"a\";",
+        "print 1 / 0;",
+        "print -\"3\";",
+        "print 1 < nil;",
+    ] {
+        let (expr, ast) = printed(source);
+        assert_eq!(literal(&ast, expr), None, "{}", source);
+    }
+
+    let output = common::run("optimizer_errors", &[], "print 2 * 3;\nprint (1 + \"a\");");
+
+    assert_eq!(output.stdout, "6\n");
+    assert!(output.stderr.contains("No Add for Number and String"));
+    assert!(output.stderr.contains(":2:10"));
+}
+
+#[test]
+fn groupings_are_removed() {
+    let (expr, ast) = printed("var a; print ((a));");
+    assert!(matches!(ast[expr], Expr::Variable(..)));
+}
+
+#[test]
+fn dead_branches_are_removed() {
+    assert!(optimized("if (false) print 1; while (nil) print 2;")
+        .0
+        .is_empty());
+
+    let (statements, ast) = optimized("if (1 > 2) print 1; else print 2;");
+    assert!(matches!(
+        statements.as_slice(),
+        [Statement::Print(expr)] if literal(&ast, *expr) == Some(&Value::Number(2.0))
+    ));
+
+    assert!(matches!(
+        optimized("var a; if (a) if (false) print 1;").0.as_slice(),
+        [Statement::Var(_), Statement::If(_, then_branch, None)]
+            if matches!(&**then_branch, Statement::Block(b) if b.is_empty())
+    ));
+}
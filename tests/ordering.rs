@@ -0,0 +1,98 @@
+//! Ordering rules for `<`, `<=`, `>` and `>=`.
+
+use lux::expr::Value;
+
+use std::{cmp::Ordering, rc::Rc};
+
+fn string(s: &str) -> Value {
+    Value::String(Rc::new(s.into()))
+}
+
+fn tuple(values: &[Value]) -> Value {
+    Value::Tuple(Rc::new(values.to_vec()))
+}
+
+#[test]
+fn numbers_and_strings() {
+    let cases = [
+        (Value::Number(1.0), Value::Number(2.0), Ordering::Less),
+        (Value::Number(-0.0), Value::Number(0.0), Ordering::Equal),
+        (string("b"), string("a"), Ordering::Greater),
+        (string("a"), string("ab"), Ordering::Less),
+        (string("B"), string("a"), Ordering::Less),
+    ];
+
+    for (a, b, expected) in cases {
+        assert_eq!(a.compare(&b), Ok(Some(expected)), "{} and {}", a, b);
+    }
+}
+
+#[test]
+fn nan_is_unordered() {
+    let nan = Value::Number(f64::NAN);
+
+    assert_eq!(nan.compare(&Value::Number(1.0)), Ok(None));
+    assert_eq!(nan.compare(&nan), Ok(None));
+}
+
+#[test]
+fn tuples_are_lexicographic() {
+    let cases = [
+        (
+            tuple(&[Value::Number(1.0), string("a")]),
+            tuple(&[Value::Number(1.0), string("b")]),
+            Ordering::Less,
+        ),
+        (
+            tuple(&[Value::Number(2.0)]),
+            tuple(&[Value::Number(1.0), string("b")]),
+            Ordering::Greater,
+        ),
+        (
+            tuple(&[Value::Number(1.0)]),
+            tuple(&[Value::Number(1.0), string("b")]),
+            Ordering::Less,
+        ),
+        (tuple(&[]), tuple(&[]), Ordering::Equal),
+    ];
+
+    for (a, b, expected) in cases {
+        assert_eq!(a.compare(&b), Ok(Some(expected)), "{} and {}", a, b);
+    }
+
+    // Elements are only compared until the first difference
+    let a = tuple(&[Value::Number(1.0), Value::Nil]);
+    let b = tuple(&[Value::Number(2.0), Value::Boolean(true)]);
+    assert_eq!(a.compare(&b), Ok(Some(Ordering::Less)));
+}
+
+#[test]
+fn mixed_and_unordered_kinds_are_errors() {
+    let values = [
+        Value::Nil,
+        Value::Boolean(true),
+        Value::Number(1.0),
+        string("a"),
+        tuple(&[Value::Number(1.0)]),
+    ];
+
+    for (i, a) in values.iter().enumerate() {
+        for (j, b) in values.iter().enumerate() {
+            let orderable = matches!(a, Value::Number(_) | Value::String(_) | Value::Tuple(_));
+
+            if i == j && orderable {
+                assert!(a.compare(b).is_ok(), "{} and {}", a, b);
+            } else {
+                assert!(a.compare(b).is_err(), "{} and {}", a, b);
+            }
+        }
+    }
+
+    assert_eq!(
+        string("a").compare(&Value::Number(1.0)),
+        Err("Can't compare string and number.".into())
+    );
+    assert!(tuple(&[string("a")])
+        .compare(&tuple(&[Value::Number(1.0)]))
+        .is_err());
+}
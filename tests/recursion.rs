@@ -0,0 +1,134 @@
+//! Calls nested too deep are an error instead of a crash.
+
+mod common;
+
+use lux::{
+    errors::CompileError, expr::Ast, interpreter::Interpreter, parser::Parser, resolver::Resolver,
+    scanner::Scanner,
+};
+
+use std::rc::Rc;
+
+/// Runs `source` with a default interpreter on the thread of the test.
+fn interpret(source: &str) -> Result<(), CompileError> {
+    let mut ast = Ast::default();
+    let mut statements = Parser::new(Scanner::new(source), &mut ast).parse().unwrap();
+    Resolver::resolve(&mut statements, &mut ast).unwrap();
+
+    Interpreter::default().interpret(&statements, &Rc::new(ast))
+}
+
+#[test]
+fn deep_recursion_is_a_stack_overflow() {
+    let output = common::run(
+        "stack_overflow",
+        &["--max-depth=50"],
+        "fn down(n) { return 1 + down(n - 1); }
+        fn start() { var n = down(10); return n; }
+        print \"before\";
+        print start();
+        print \"after\";",
+    );
+
+    assert_eq!(output.stdout, "before\n");
+    assert!(output
+        .stderr
+        .contains("Stack overflow, more than 50 nested calls: down (50 times), start."));
+    assert!(output.stderr.contains(":1:35"));
+}
+
+#[test]
+fn the_summary_shows_the_innermost_frames() {
+    let output = common::run(
+        "stack_overflow_cycle",
+        &["--max-depth=20"],
+        "fn a(n) { return 1 + b(n); }
+        fn b(n) { return 1 + c(n); }
+        fn c(n) { return 1 + a(n); }
+        a(0);",
+    );
+
+    assert!(output
+        .stderr
+        .contains("Stack overflow, more than 20 nested calls: c, b, a, ...."));
+}
+
+#[test]
+fn the_default_depth_allows_deep_recursion() {
+    let output = common::run(
+        "deep_recursion",
+        &[],
+        "fn sum(n) { if (n == 0) return 0; return n + sum(n - 1); }
+        print sum(5000);",
+    );
+
+    assert_eq!(output.stdout, "12502500\n");
+    assert!(output.stderr.is_empty());
+}
+
+/// A recursive function whose call is nested in blocks, a match and an
+/// expression, each of them takes stack as well.
+fn nested_down(n: usize) -> String {
+    format!(
+        "fn down(n) {{
+            if (n == 0) return 0;
+            {{ {{ {{ {{ {{ {{ {{ {{ {{
+                match (n) {{
+                    else => {{
+                        {{ {{ {{ {{ {{
+                            var r = 1 + down(n - 1);
+                            return r;
+                        }} }} }} }} }}
+                    }}
+                }}
+            }} }} }} }} }} }} }} }} }}
+        }}
+        print \"before\";
+        print down({});",
+        n
+    )
+}
+
+#[test]
+fn nesting_inside_calls_is_a_stack_overflow() {
+    let output = common::run_once(
+        "nested_stack_overflow",
+        &["--backend=tree", "--max-depth=2500"],
+        &nested_down(2000),
+    );
+
+    assert_eq!(output.stdout, "before\n");
+    assert!(output
+        .stderr
+        .contains("Stack overflow, nested too deeply: down ("));
+}
+
+#[test]
+fn nested_calls_within_the_default_depth_run() {
+    let output = common::run_once(
+        "nested_default_depth",
+        &["--backend=tree"],
+        &nested_down(1000),
+    );
+
+    assert_eq!(output.stdout, "before\n1000\n");
+    assert!(output.stderr.is_empty(), "{}", output.stderr);
+}
+
+#[test]
+fn embedded_interpreters_fit_the_stack_of_their_thread() {
+    assert!(
+        interpret("fn down(n) { if (n == 0) return 0; return 1 + down(n - 1); } down(10);").is_ok()
+    );
+
+    match interpret("fn up(n) { return 1 + up(n + 1); } up(0);") {
+        Err(CompileError::Interpreter(_, msg)) => {
+            assert!(
+                msg.starts_with("Stack overflow, nested too deeply: up ("),
+                "{}",
+                msg
+            )
+        }
+        other => panic!("expected a stack overflow, got {:?}", other),
+    }
+}
@@ -0,0 +1,24 @@
+//! The interactive prompt, one line at a time.
+
+mod common;
+
+#[test]
+fn lines_see_what_earlier_lines_declared() {
+    for backend in ["--backend=tree", "--backend=vm"] {
+        let output = common::repl(
+            &[backend],
+            "fn next(n) { return n + 1; }\nvar two = next(1);\nprint next(two);\n",
+        );
+
+        assert_eq!(output.stdout, ">>> >>> >>> 3\n>>> \n");
+        assert!(output.stderr.is_empty());
+    }
+}
+
+#[test]
+fn errors_do_not_end_the_session() {
+    let output = common::repl(&[], "print nil + 1;\nprint 2;\n");
+
+    assert!(output.stdout.ends_with("2\n>>> \n"));
+    assert!(output.stderr.contains("No Add for the given values"));
+}
@@ -0,0 +1,144 @@
+//! Variables are bound lexically, where the code is written.
+
+mod common;
+
+#[test]
+fn closures_do_not_see_later_declarations() {
+    let output = common::run(
+        "later_declarations",
+        &[],
+        r#"
+        var a = "global";
+        {
+          fn show() { print a; }
+          show();
+          var a = "block";
+          show();
+          print a;
+        }
+        "#,
+    );
+
+    assert_eq!(output.stdout, "global\nglobal\nblock\n");
+}
+
+#[test]
+fn closures_keep_their_variables() {
+    let output = common::run(
+        "closures",
+        &[],
+        r#"
+        fn makeCounter() {
+          var i = 0;
+          fn count() {
+            i = i + 1;
+            return i;
+          }
+          return count;
+        }
+
+        var first = makeCounter();
+        var second = makeCounter();
+        print first();
+        print first();
+        print second();
+        "#,
+    );
+
+    assert_eq!(output.stdout, "1\n2\n1\n");
+}
+
+#[test]
+fn match_bindings_and_parameters_shadow_outer_variables() {
+    let output = common::run(
+        "shadowing",
+        &[],
+        r#"
+        enum Box { Of(x) }
+        var x = "outer";
+
+        fn show(x) {
+          match (Box.Of(x + 1)) {
+            Box.Of(x) => print x;
+          }
+          print x;
+        }
+
+        show(1);
+        print x;
+        "#,
+    );
+
+    assert_eq!(output.stdout, "2\n1\nouter\n");
+}
+
+#[test]
+fn locals_declared_in_nested_scopes_keep_their_values() {
+    let output = common::run(
+        "slots",
+        &[],
+        r#"
+        fn outer(a, b) {
+          var c = a + b;
+          enum Shape { Dot, Line(length) }
+          {
+            var d = c * 2;
+            var e, f = (d, a);
+            fn inner() {
+              e = e + 1;
+              return Shape.Line(e + f);
+            }
+            match (inner()) {
+              Shape.Line(length) => print length;
+              else => print "no";
+            }
+            print e;
+          }
+          print c;
+        }
+
+        outer(1, 2);
+        "#,
+    );
+
+    assert_eq!(output.stdout, "8\n7\n3\n");
+}
+
+#[test]
+fn undefined_globals_are_reported_where_they_are_used() {
+    let output = common::run(
+        "undefined_global",
+        &[],
+        r#"
+        fn f() {
+          var local = 1;
+          {
+            return local + missing;
+          }
+        }
+        f();
+        "#,
+    );
+
+    assert!(output
+        .stderr
+        .contains("Runtime error: Undefined variable or function: missing."));
+    assert!(output.stderr.contains(":5:28"));
+
+    let output = common::run(
+        "undefined_assignment",
+        &[],
+        r#"
+        fn f() {
+          var local = 1;
+          missing = local;
+        }
+        f();
+        "#,
+    );
+
+    assert!(output
+        .stderr
+        .contains("Runtime error: Undefined variable: missing."));
+    assert!(output.stderr.contains(":4:11"));
+}
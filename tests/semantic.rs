@@ -0,0 +1,63 @@
+//! Code rejected before it runs.
+
+mod common;
+
+use lux::{errors::CompileError, expr::Ast, parser::Parser, resolver::Resolver, scanner::Scanner};
+
+fn errors(source: &str) -> Vec<String> {
+    let mut ast = Ast::default();
+    let mut statements = Parser::new(Scanner::new(source), &mut ast).parse().unwrap();
+
+    match Resolver::resolve(&mut statements, &mut ast) {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors
+            .into_iter()
+            .map(|e| match e {
+                CompileError::Resolver(_, msg) => msg,
+                other => panic!("unexpected error: {:?}", other),
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn every_error_is_reported() {
+    assert_eq!(
+        errors(
+            "return 1;
+            { var a = 1; var a = 2; }
+            { var b = b; }
+            fn f(x, x) { return this; }
+            print super.method;"
+        ),
+        [
+            "Can't return from top-level code.",
+            "Already a variable named a in this scope.",
+            "Can't read a local variable in its own initializer.",
+            "Already a variable named x in this scope.",
+            "Can't use 'this' outside of a class.",
+            "Can't use 'super' outside of a class.",
+        ]
+    );
+}
+
+#[test]
+fn valid_code_is_accepted() {
+    assert!(errors(
+        "var a = 1;
+        var a = a + 1;
+        { var b = a; { var b = a + 1; print b; } }
+        fn f(x) { if (x) return x; { return; } }
+        enum E { A(x, y) }
+        match (E.A(1, 2)) { E.A(x, y) => print x + y; }"
+    )
+    .is_empty());
+}
+
+#[test]
+fn nothing_runs_when_there_are_errors() {
+    let output = common::run("semantic", &[], "print 1;\nreturn;");
+
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.contains("Can't return from top-level code."));
+}
@@ -0,0 +1,89 @@
+//! `return f(args);` does not grow the stack.
+
+mod common;
+
+#[test]
+fn tail_recursion_runs_in_constant_stack() {
+    let output = common::run(
+        "tail_recursion",
+        &[],
+        "fn count(n, acc) {
+            if (n == 0) return acc;
+            return count(n - 1, acc + 1);
+        }
+        print count(1000000, 0);
+
+        fn even(n) { if (n == 0) return true; return odd(n - 1); }
+        fn odd(n) { if (n == 0) return false; return even(n - 1); }
+        print even(100001);",
+    );
+
+    assert_eq!(output.stdout, "1000000\nfalse\n");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn tail_calls_to_closures_keep_their_variables() {
+    let output = common::run(
+        "tail_closures",
+        &[],
+        "fn outer() {
+            var x = \"captured\";
+            fn inner() { return x; }
+            return inner();
+        }
+        enum Box { Of(value) }
+        fn wrap(v) { return Box.Of(v); }
+        print outer();
+        print wrap(1);",
+    );
+
+    assert_eq!(output.stdout, "captured\nBox.Of(1)\n");
+}
+
+#[test]
+fn return_annotations_of_every_tail_call_are_checked() {
+    let output = common::run(
+        "tail_types",
+        &["--strict"],
+        "fn f(n) -> number { if (n == 0) return \"done\"; return f(n - 1); }
+        fn g(n) -> string { return f(n); }
+        print g(3);",
+    );
+
+    assert!(output.stdout.is_empty());
+    assert!(output
+        .stderr
+        .contains("Type mismatch: expected number, found string"));
+
+    let output = common::run(
+        "tail_types_outer",
+        &["--strict"],
+        "fn f() { return 1; }
+        fn g() -> string { return f(); }
+        print g();",
+    );
+
+    assert!(output
+        .stderr
+        .contains("Type mismatch: expected string, found number"));
+}
+
+#[test]
+fn mutual_tail_calls_check_each_annotation_once() {
+    let output = common::run(
+        "tail_types_mutual",
+        &["--strict"],
+        "fn even(n) -> bool { if (n == 0) return true; return odd(n - 1); }
+        fn odd(n) -> bool { if (n == 0) return false; return even(n - 1); }
+        print even(100001);
+        fn ping(n) -> number { if (n == 0) return \"done\"; return pong(n - 1); }
+        fn pong(n) -> number { return ping(n); }
+        print ping(100000);",
+    );
+
+    assert_eq!(output.stdout, "false\n");
+    assert!(output
+        .stderr
+        .contains("Type mismatch: expected number, found string"));
+}
@@ -0,0 +1,103 @@
+//! Tuples, multiple return values and unpacking them.
+
+mod common;
+
+#[test]
+fn tuple_literals() {
+    let output = common::run(
+        "tuple_literals",
+        &[],
+        "print (1, \"two\", nil);
+        print (1,);
+        print ((1, 2), (3,));
+        print (1);
+        print ();",
+    );
+
+    assert_eq!(
+        output.stdout,
+        "(1, \"two\", nil)\n(1,)\n((1, 2), (3,))\n1\n()\n"
+    );
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn functions_return_several_values() {
+    let output = common::run(
+        "tuple_return",
+        &[],
+        "fn divide(a, b) { return a / b, a % b; }
+        var q, r = divide(7, 2);
+        print q;
+        print r;
+        print divide(9, 3);",
+    );
+
+    assert_eq!(output.stdout, "3.5\n1\n(3, 0)\n");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn unpacking_declares_every_name() {
+    let output = common::run(
+        "tuple_unpack",
+        &[],
+        "var a, b = (1, 2);
+        {
+            var a, c = (b + 1, b + 2);
+            print (a, b, c);
+        }
+        print (a, b);",
+    );
+
+    assert_eq!(output.stdout, "(3, 2, 4)\n(1, 2)\n");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn unpacking_needs_a_tuple_of_the_same_size() {
+    let output = common::run("tuple_too_few", &[], "var a, b, c = (1, 2);");
+    assert!(output
+        .stderr
+        .contains("Expected 3 values to unpack but got 2."));
+
+    let output = common::run("tuple_too_many", &[], "var a, b = (1, 2, 3);");
+    assert!(output
+        .stderr
+        .contains("Expected 2 values to unpack but got 3."));
+
+    let output = common::run("tuple_not_a_tuple", &[], "var a, b = 1;");
+    assert!(output.stderr.contains("Can only unpack tuples, got 1."));
+}
+
+#[test]
+fn elements_are_evaluated_in_order() {
+    let output = common::run(
+        "tuple_order",
+        &[],
+        "fn show(n) { print n; return n; }
+        var t = (show(1), show(2), show(3));
+        fn pair() { return show(\"a\"), show(\"b\"); }
+        var x, y = pair();
+        print (t, x, y);",
+    );
+
+    assert_eq!(output.stdout, "1\n2\n3\na\nb\n((1, 2, 3), \"a\", \"b\")\n");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn tuples_compare_element_by_element() {
+    let output = common::run(
+        "tuple_compare",
+        &[],
+        "print (1, 2) == (1, 2);
+        print (1, 2) == (1, 2, 3);
+        print (1, \"a\") < (1, \"b\");
+        print (2,) > (1, 9);
+        print (1,) < (1, 0);",
+    );
+
+    assert_eq!(output.stdout, "true\nfalse\ntrue\ntrue\ntrue\n");
+    assert!(output.stderr.is_empty());
+}
@@ -0,0 +1,132 @@
+//! Type annotations, checked by `lux check` and at calls with `--strict`.
+
+mod common;
+
+#[test]
+fn unknown_types_are_rejected_before_running() {
+    let source = "print 0;\nvar x: nubmer = 1;\nprint x;";
+
+    let check = common::run_once("unknown_type_check", &["check"], source);
+    let strict = common::run("unknown_type_strict", &["--strict"], source);
+    let permissive = common::run("unknown_type", &[], source);
+
+    for output in [check, strict, permissive] {
+        assert!(output.stdout.is_empty());
+        assert!(output.stderr.contains("Unknown type 'nubmer'."));
+        assert!(output.stderr.contains("2:8"), "{}", output.stderr);
+    }
+}
+
+#[test]
+fn enums_declared_anywhere_are_types() {
+    let output = common::run(
+        "enum_types",
+        &["--strict"],
+        "fn first(c: Color) -> Color { return c; }
+        {
+            enum Color { Red, Green }
+            var c: Color = first(Color.Green);
+            print c;
+        }",
+    );
+
+    assert_eq!(output.stdout, "Color.Green\n");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn the_repl_knows_the_enums_of_earlier_lines() {
+    let output = common::repl(
+        &["--strict"],
+        "enum Color { Red }\nvar c: Color = Color.Red;\nvar d: Colour = c;\n",
+    );
+
+    assert!(!output.stderr.contains("Unknown type 'Color'."));
+    assert!(output.stderr.contains("Unknown type 'Colour'."));
+}
+
+const ADD: &str = "fn add(a: number, b: number) -> number { return a + b; }\n";
+
+#[test]
+fn check_reports_both_spans_without_running() {
+    let source = format!("{}print add(1, \"2\");", ADD);
+    let output = common::run_once("check_mismatch", &["check"], &source);
+
+    assert!(output.stdout.is_empty());
+    assert!(output
+        .stderr
+        .contains("Type mismatch: expected number, found string"));
+    assert!(output.stderr.contains("this is string"));
+    assert!(output.stderr.contains("expected number because of this"));
+    // The argument, and the line of the annotation it is checked against
+    assert!(output.stderr.contains("2:14"), "{}", output.stderr);
+    assert!(output.stderr.contains(ADD.trim_end()));
+}
+
+#[test]
+fn check_reports_every_mismatch() {
+    let output = common::run_once(
+        "check_every",
+        &["check"],
+        "var name: string = 1;
+        fn twice(n: number) -> number { return \"twice\"; }
+        name = nil;",
+    );
+
+    assert_eq!(output.stderr.matches("Type mismatch").count(), 3);
+}
+
+#[test]
+fn check_accepts_well_typed_code() {
+    let source = format!(
+        "{}var three: number = add(1, 2);\nprint add(three, 4);",
+        ADD
+    );
+    let output = common::run_once("check_ok", &["check"], &source);
+
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn unannotated_variables_are_any() {
+    // The type of the initializer is not kept, so they can change
+    let source = format!(
+        "{}var n = \"one\";\nn = 1;\nvar s: string = n;\nprint add(n, 2);",
+        ADD
+    );
+    let output = common::run_once("check_any", &["check"], &source);
+
+    assert!(output.stderr.is_empty(), "{}", output.stderr);
+}
+
+#[test]
+fn strict_mode_checks_arguments_at_calls() {
+    let source = format!(
+        "{}print add(1, 2);\nvar two = \"2\";\nprint add(1, two);",
+        ADD
+    );
+    let output = common::run("strict_arguments", &["--strict"], &source);
+
+    assert_eq!(output.stdout, "3\n");
+    assert!(output
+        .stderr
+        .contains("Type mismatch: expected number, found string"));
+}
+
+#[test]
+fn strict_mode_checks_returned_values() {
+    let source = "fn name(n) -> string { return n; }
+    print name(\"lux\");
+    print name(1);";
+
+    let strict = common::run("strict_returns", &["--strict"], source);
+    let permissive = common::run("permissive_returns", &[], source);
+
+    assert_eq!(strict.stdout, "lux\n");
+    assert!(strict
+        .stderr
+        .contains("Type mismatch: expected string, found number"));
+    assert_eq!(permissive.stdout, "lux\n1\n");
+    assert!(permissive.stderr.is_empty());
+}
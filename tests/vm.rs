@@ -0,0 +1,133 @@
+//! The bytecode virtual machine, `common::run` checks that it agrees with the
+//! tree walking interpreter.
+
+mod common;
+
+use std::{process::Command, time::Instant};
+
+#[test]
+fn closures_share_captured_variables() {
+    let output = common::run(
+        "vm_closures",
+        &[],
+        "fn counter() {
+            var n = 0;
+            fn inc() { n = n + 1; return n; }
+            fn get() { return n; }
+            return inc, get;
+        }
+        var inc, get = counter();
+        inc();
+        inc();
+        print get();
+
+        fn outer() {
+            var x = \"x\";
+            fn middle() {
+                fn inner() { x = x + \"!\"; return x; }
+                return inner;
+            }
+            return middle();
+        }
+        var shout = outer();
+        shout();
+        print shout();",
+    );
+
+    assert_eq!(output.stdout, "2\nx!!\n");
+}
+
+#[test]
+fn every_iteration_captures_its_own_variables() {
+    let output = common::run(
+        "vm_loops",
+        &[],
+        "var fns = ();
+        for (var i = 0; i < 3; i = i + 1) {
+            var j = i;
+            fn f() { return j; }
+            fns = (f, fns);
+        }
+        var f, rest = fns;
+        var g, rest = rest;
+        print f() + g();",
+    );
+
+    assert_eq!(output.stdout, "3\n");
+}
+
+#[test]
+fn match_arms_bind_payloads() {
+    let output = common::run(
+        "vm_match",
+        &[],
+        "enum Shape { Circle(r), Rect(w, h), Empty }
+        fn area(s) {
+            match (s) {
+                Shape.Circle(r) => return 3 * r * r;
+                Shape.Rect(w, h) => { var a = w * h; return a; }
+                else => return 0;
+            }
+        }
+        print area(Shape.Circle(2));
+        print area(Shape.Rect(2, 5));
+        print area(Shape.Empty);",
+    );
+
+    assert_eq!(output.stdout, "12\n10\n0\n");
+}
+
+#[test]
+fn errors_point_at_the_same_code() {
+    let output = common::run(
+        "vm_errors",
+        &[],
+        "fn f(a) { return a + \"s\"; }\nprint f(1);",
+    );
+
+    assert!(output.stderr.contains("No Add for Number and String"));
+    assert!(output.stderr.contains(":1:20"));
+
+    let output = common::run(
+        "vm_type_errors",
+        &["--strict"],
+        "fn f(a: number) -> string { return a; }\nprint f(1);",
+    );
+
+    assert!(output
+        .stderr
+        .contains("Type mismatch: expected string, found number"));
+}
+
+/// `cargo test --release -- --ignored`
+#[test]
+#[ignore]
+fn vm_is_several_times_faster() {
+    let script = std::env::temp_dir().join("lux_test_vm_fibonacci.lux");
+    std::fs::write(
+        &script,
+        "fn fibonacci(n) {
+            if (n <= 1) { return n; }
+            return fibonacci(n - 2) + fibonacci(n - 1);
+        }
+        print fibonacci(30);",
+    )
+    .unwrap();
+
+    let time = |flags: &[&str]| {
+        let start = Instant::now();
+        let output = Command::new(env!("CARGO_BIN_EXE_lux"))
+            .args(flags)
+            .arg(&script)
+            .output()
+            .unwrap();
+
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "832040\n");
+        start.elapsed()
+    };
+
+    let tree = time(&[]);
+    let vm = time(&["--backend=vm"]);
+
+    assert!(vm * 3 < tree, "tree: {:?}, vm: {:?}", tree, vm);
+}
@@ -0,0 +1,120 @@
+//! Lints over valid code and the flags and pragmas that control them.
+
+mod common;
+
+use lux::{
+    errors::CompileError,
+    expr::Ast,
+    lints::{Lint, Linter, Warnings},
+    parser::Parser,
+    resolver::Resolver,
+    scanner::Scanner,
+};
+
+use std::collections::HashSet;
+
+/// The messages of the warnings reported with every lint on.
+fn warnings(source: &str) -> Vec<String> {
+    let mut ast = Ast::default();
+    let mut statements = Parser::new(Scanner::new(source), &mut ast).parse().unwrap();
+    Resolver::resolve(&mut statements, &mut ast).unwrap();
+
+    let all = Warnings {
+        enabled: HashSet::from(Lint::ALL),
+        fatal: false,
+    };
+
+    all.filter(source, Linter::check(&statements, &ast))
+        .into_iter()
+        .map(|w| match w {
+            CompileError::Warning(_, _, msg) => msg,
+            other => panic!("unexpected error: {:?}", other),
+        })
+        .collect()
+}
+
+#[test]
+fn unused_locals_and_parameters() {
+    assert_eq!(
+        warnings(
+            "var global = 1;
+            fn f(used, unused) {
+                var assigned = 1;
+                assigned = 2;
+                return used;
+            }"
+        ),
+        ["Unused parameter unused.", "Unused variable assigned."]
+    );
+}
+
+#[test]
+fn statements_after_return() {
+    assert_eq!(
+        warnings("fn f() { return 1; print 2; print 3; }"),
+        ["Unreachable statement."]
+    );
+}
+
+#[test]
+fn shadowing() {
+    assert_eq!(
+        warnings("var a = 1; fn f(a) { { var a = 2; print a; } return a; }"),
+        [
+            "a shadows a variable from an outer scope.",
+            "a shadows a variable from an outer scope.",
+        ]
+    );
+}
+
+#[test]
+fn constant_conditions() {
+    assert_eq!(
+        warnings("if ((false)) print 1; while (nil) print 2; while (true) print 3;"),
+        ["Condition is always false.", "Condition is always false."]
+    );
+}
+
+#[test]
+fn assignment_to_undeclared_globals() {
+    assert_eq!(
+        warnings("fn f() { declared = 1; typo = 2; } var declared;"),
+        ["Assignment to undeclared variable typo."]
+    );
+}
+
+#[test]
+fn pragmas_silence_their_line_or_the_next() {
+    assert!(warnings(
+        "fn f(a) { // lux-allow(unused)
+            var b = 1; // lux-allow(unused)
+            // lux-allow(shadowing, unused)
+            var f = 2;
+        }"
+    )
+    .is_empty());
+}
+
+const SOURCE: &str = "fn f(x) { return 1; print 2; }\nprint f(0);";
+
+#[test]
+fn only_enabled_warnings_are_reported() {
+    let output = common::run("warnings_default", &[], SOURCE);
+
+    assert!(output.stderr.contains("Unreachable statement."));
+    assert!(!output.stderr.contains("Unused parameter x."));
+    assert_eq!(output.stdout, "1\n");
+
+    let output = common::run("warnings_toggled", &["-W", "-Wno-unreachable"], SOURCE);
+
+    assert!(!output.stderr.contains("Unreachable statement."));
+    assert!(output.stderr.contains("Unused parameter x."));
+}
+
+#[test]
+fn werror_stops_the_script() {
+    let output = common::run("warnings_werror", &["-Werror"], SOURCE);
+
+    assert!(output.stderr.contains("Unreachable statement."));
+    assert!(output.stdout.is_empty());
+}